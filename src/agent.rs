@@ -0,0 +1,624 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
+use rand::{thread_rng, Rng, SeedableRng, StdRng};
+use algo::{search_reporting, endgame, ismcts, Endgame, Evaluator, Flipped, Negated, Outcome, SearchConfig, SearchResult, Stop};
+use algo::State as AlgoState;
+use book::Book;
+use eval::{Weights, WeightedEvaluator};
+use record::{GameRecord, Setup};
+use state::{Move, State};
+
+// How many plies the endgame solver may search before falling back to the search
+pub const ENDGAME_DEPTH: usize = 8;
+
+// Something that can play a seat: a bot, a person or a script
+pub trait Agent {
+    // Who is playing, for game records
+    fn name(&self) -> String;
+    // The move to make in `state`, in which it's the agent's turn. `None` gives up the game.
+    fn choose(&mut self, state: &State) -> Option<Move>;
+    // Called after every move by either side, with the position after it
+    fn observe(&mut self, _mov: &Move, _state: &State) {}
+}
+
+// The best moves found for a position and how they were found
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Thought {
+    pub moves: Vec<Move>,
+    pub info: String,
+}
+
+// Moves in text notation, separated by commas
+pub fn line(moves: &[Move]) -> String {
+    moves.iter().map(|mov| mov.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// Searches from the side of the player to move, the score is from their side too
+pub fn search_side(state: &mut State, evaluator: &WeightedEvaluator, config: &SearchConfig) -> SearchResult<Move, i64> {
+    search_side_reporting(state, evaluator, config, |_| ())
+}
+
+// Like `search_side`, calling `report` with the result of every depth, see `search_reporting`
+pub fn search_side_reporting<R: FnMut(&SearchResult<Move, i64>)>(state: &mut State, evaluator: &WeightedEvaluator, config: &SearchConfig, report: R) -> SearchResult<Move, i64> {
+    if state.players_turn {
+        search_reporting(state, evaluator, config, report)
+    } else {
+        search_reporting(state, &Negated(evaluator.clone()), config, report)
+    }
+}
+
+// Searches for the side to move. With `solve` an endgame is solved exactly first, the outcome is
+// from the side to move too. The solver doesn't know which cards will be dealt to the market, so a
+// result is only forced as long as none of them changes it. The time the
+// solver took is taken from the time limit of the search, the node limit applies to both.
+pub fn think(state: &mut State, evaluator: &WeightedEvaluator, config: &SearchConfig, solve: bool) -> Thought {
+    let start = Instant::now();
+    // A proven loss is left to the heuristic search, which at least makes it hard to win
+    let solved = if solve && state.is_endgame() {
+        // Outcomes are from the player's side, so the adversary solves the game flipped
+        let result = if state.players_turn {
+            endgame(state, ENDGAME_DEPTH, config)
+        } else {
+            endgame(&mut Flipped(state), ENDGAME_DEPTH, config)
+        };
+        result.and_then(|result| if result.score == Outcome::Loss { None } else { Some(result) })
+    } else {
+        None
+    };
+    if let Some(result) = solved {
+        return Thought {
+            info: format!("{} forced in {} plies, nodes {} cutoffs {} time {:?}\npv {}", result.score, result.depth, result.nodes, result.cutoffs, result.elapsed, line(&result.pv)),
+            moves: result.moves,
+        };
+    }
+
+    let config = SearchConfig {
+        time_limit: config.time_limit.map(|time_limit| time_limit.checked_sub(start.elapsed()).unwrap_or_default()),
+        ..config.clone()
+    };
+    let result = search_side(state, evaluator, &config);
+    Thought {
+        info: format!("score {} depth {} nodes {} cutoffs {} time {:?}\npv {}", result.score, result.depth, result.nodes, result.cutoffs, result.elapsed, line(&result.pv)),
+        moves: result.moves,
+    }
+}
+
+// Thinks about the replies to the opponent's most promising moves while they are deciding
+struct Ponder {
+    stop: Stop,
+    handle: thread::JoinHandle<HashMap<Move, Thought>>,
+}
+
+impl Ponder {
+    fn start(state: &State, evaluator: &WeightedEvaluator, config: &SearchConfig, solve: bool) -> Ponder {
+        let stop = Stop::new();
+        let mut state = state.clone();
+        let evaluator = evaluator.clone();
+        let config = SearchConfig {
+            stop: stop.clone(),
+            ..config.clone()
+        };
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut thoughts = HashMap::new();
+                let mut replies = state.generate_moves();
+                // The player maximizes the score and the adversary minimizes it
+                let side = if state.players_turn { -1 } else { 1 };
+                replies.sort_by_key(|mov| {
+                    state.apply(mov);
+                    let score = evaluator.evaluate(&state);
+                    state.undo(mov);
+                    side * score
+                });
+
+                for mov in replies.into_iter() {
+                    let mut next = state.clone();
+                    next.apply(&mov);
+                    next.refill_market();
+                    if next.is_terminal() {
+                        continue;
+                    }
+                    let thought = think(&mut next, &evaluator, &config, solve);
+                    // A search that was stopped halfway isn't worth keeping
+                    if stop.is_stopped() {
+                        break;
+                    }
+                    thoughts.insert(mov, thought);
+                }
+                thoughts
+            })
+        };
+
+        Ponder {
+            stop,
+            handle,
+        }
+    }
+
+    // Stops the search that is running and returns the replies that were searched completely
+    fn finish(self) -> HashMap<Move, Thought> {
+        self.stop.stop();
+        self.handle.join().unwrap()
+    }
+}
+
+// A bot that searches with `search`, after looking in its opening book
+pub struct SearchAgent {
+    pub name: String,
+    pub search: SearchConfig,
+    pub evaluator: WeightedEvaluator,
+    pub book: Option<Book>,
+    // Solve endgames exactly, see `think`
+    pub solve_endgames: bool,
+    // Think about replies on the opponent's time
+    pub ponder: bool,
+    // Print the thought behind every move
+    pub verbose: bool,
+    // Breaks ties between moves
+    rng: StdRng,
+    // Whether the agent plays as the player, known after its first move
+    side: Option<bool>,
+    pondering: Option<Ponder>,
+    pondered: Option<Thought>,
+}
+
+impl SearchAgent {
+    pub fn new(name: &str, search: SearchConfig, weights: Weights) -> SearchAgent {
+        SearchAgent {
+            name: name.to_string(),
+            search,
+            evaluator: WeightedEvaluator::new(weights),
+            book: None,
+            solve_endgames: false,
+            ponder: false,
+            verbose: false,
+            rng: SeedableRng::from_seed(&[thread_rng().gen::<usize>()][..]),
+            side: None,
+            pondering: None,
+            pondered: None,
+        }
+    }
+
+    // Breaks ties the same way every time, so games can be reproduced
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = SeedableRng::from_seed(&[seed as usize][..]);
+    }
+}
+
+impl Agent for SearchAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        self.side = Some(state.players_turn);
+        let pondered = self.pondered.take();
+        let book_move = self.book.as_ref().and_then(|book| book.lookup(state));
+        let thought = match (book_move, pondered) {
+            (Some(mov), _) => Thought {
+                moves: vec![mov],
+                info: "(book)".to_string(),
+            },
+            (None, Some(thought)) => Thought {
+                info: format!("(pondered) {}", thought.info),
+                ..thought
+            },
+            (None, None) => think(&mut state.clone(), &self.evaluator, &self.search, self.solve_endgames),
+        };
+        if self.verbose {
+            println!("{}", thought.info);
+        }
+        self.search.tie_break.pick(&thought.moves, &mut self.rng).cloned()
+    }
+
+    fn observe(&mut self, mov: &Move, state: &State) {
+        let side = match self.side {
+            Some(side) => side,
+            None => return,
+        };
+        if state.players_turn == side {
+            if let Some(ponder) = self.pondering.take() {
+                self.pondered = ponder.finish().remove(mov);
+            }
+        } else if self.ponder && !state.is_terminal() {
+            self.pondering = Some(Ponder::start(state, &self.evaluator, &self.search, self.solve_endgames));
+        }
+    }
+}
+
+impl Drop for SearchAgent {
+    fn drop(&mut self) {
+        if let Some(ponder) = self.pondering.take() {
+            ponder.finish();
+        }
+    }
+}
+
+// The bots there are, by the name they're chosen with
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Kind {
+    // A `SearchAgent`
+    Search,
+    // An `IsmctsAgent`
+    Ismcts,
+    // The baselines, `RandomAgent` and `GreedyAgent`
+    Random,
+    Greedy,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kind::Search => write!(f, "search"),
+            Kind::Ismcts => write!(f, "ismcts"),
+            Kind::Random => write!(f, "random"),
+            Kind::Greedy => write!(f, "greedy"),
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        match s {
+            "search" => Ok(Kind::Search),
+            "ismcts" => Ok(Kind::Ismcts),
+            "random" => Ok(Kind::Random),
+            "greedy" => Ok(Kind::Greedy),
+            _ => Err(format!("unknown bot {:?}, expected search, ismcts, random or greedy", s)),
+        }
+    }
+}
+
+// A bot that plays the move information set Monte Carlo tree search visited most, see `ismcts`. Of
+// `search` only the time and node limits, the stop flag and the tie break are used.
+pub struct IsmctsAgent {
+    pub name: String,
+    pub search: SearchConfig,
+    pub evaluator: WeightedEvaluator,
+    // Print the thought behind every move
+    pub verbose: bool,
+    // Deals the determinizations, plays the playouts and breaks ties
+    rng: StdRng,
+}
+
+impl IsmctsAgent {
+    pub fn new(name: &str, search: SearchConfig, weights: Weights, seed: u64) -> IsmctsAgent {
+        IsmctsAgent {
+            name: name.to_string(),
+            search,
+            evaluator: WeightedEvaluator::new(weights),
+            verbose: false,
+            rng: SeedableRng::from_seed(&[seed as usize][..]),
+        }
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        let result = if state.players_turn {
+            ismcts(state, &self.evaluator, &self.search, &mut self.rng)
+        } else {
+            ismcts(state, &Negated(self.evaluator.clone()), &self.search, &mut self.rng)
+        };
+        if self.verbose {
+            println!("won {:.3} depth {} nodes {} time {:?}", result.score, result.depth, result.nodes, result.elapsed);
+        }
+        self.search.tie_break.pick(&result.moves, &mut self.rng).cloned()
+    }
+}
+
+// Plays a legal move picked uniformly at random, as the weakest opponent there is
+pub struct RandomAgent {
+    pub name: String,
+    rng: StdRng,
+}
+
+impl RandomAgent {
+    pub fn new(name: &str, seed: u64) -> RandomAgent {
+        RandomAgent {
+            name: name.to_string(),
+            rng: SeedableRng::from_seed(&[seed as usize][..]),
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        self.rng.choose(&state.generate_moves()).cloned()
+    }
+}
+
+// Plays the move with the best `score` right after it, looking no further. Ties are broken at
+// random.
+pub struct GreedyAgent {
+    pub name: String,
+    rng: StdRng,
+}
+
+impl GreedyAgent {
+    pub fn new(name: &str, seed: u64) -> GreedyAgent {
+        GreedyAgent {
+            name: name.to_string(),
+            rng: SeedableRng::from_seed(&[seed as usize][..]),
+        }
+    }
+}
+
+impl Agent for GreedyAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        let mut state = state.clone();
+        // Scores are from the player's side, the adversary wants them low
+        let side = if state.players_turn { 1 } else { -1 };
+        let mut best = Vec::new();
+        let mut best_score = None;
+        for mov in state.generate_moves().into_iter() {
+            state.apply(&mov);
+            let score = side * state.score();
+            state.undo(&mov);
+            if best_score.is_none_or(|best_score| score > best_score) {
+                best_score = Some(score);
+                best.clear();
+            }
+            if best_score == Some(score) {
+                best.push(mov);
+            }
+        }
+        self.rng.choose(&best).cloned()
+    }
+}
+
+// A person typing moves in the notation of `Move` on the terminal
+pub struct HumanAgent {
+    pub name: String,
+}
+
+impl HumanAgent {
+    pub fn new(name: &str) -> HumanAgent {
+        HumanAgent {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Agent for HumanAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    // Shows the board and asks until a legal move is typed. The end of the input gives up.
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        let mut stdout = io::stdout();
+        let _ = state.print(&mut stdout);
+        loop {
+            println!("Your move, like take KBG, take 2R drop W, buy 3.2 noble 1, reserve 1.4, buyres 2 or pass");
+            let _ = stdout.flush();
+            let mut buf = String::new();
+            if io::stdin().read_line(&mut buf).unwrap_or(0) == 0 {
+                return None;
+            }
+            if buf.trim().is_empty() {
+                continue;
+            }
+            match state.parse_move(buf.trim()) {
+                Ok(mov) => return Some(mov),
+                Err(err) => println!("{}", err),
+            }
+        }
+    }
+}
+
+// Plays a list of moves in notation, then gives up. Also gives up on a move that isn't legal.
+pub struct ScriptedAgent {
+    pub name: String,
+    moves: Vec<String>,
+    next: usize,
+}
+
+impl ScriptedAgent {
+    pub fn new<S: ToString>(name: &str, moves: &[S]) -> ScriptedAgent {
+        ScriptedAgent {
+            name: name.to_string(),
+            moves: moves.iter().map(|mov| mov.to_string()).collect(),
+            next: 0,
+        }
+    }
+}
+
+impl Agent for ScriptedAgent {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose(&mut self, state: &State) -> Option<Move> {
+        let mov = self.moves.get(self.next)?;
+        self.next += 1;
+        state.parse_move(mov).ok()
+    }
+}
+
+// Runs a game between two agents, the first one moving as the player
+pub struct Game {
+    pub state: State,
+    pub record: GameRecord,
+    agents: [Box<dyn Agent>; 2],
+}
+
+impl Game {
+    pub fn new(setup: Setup, player: Box<dyn Agent>, adversary: Box<dyn Agent>) -> Game {
+        Game {
+            state: setup.state(),
+            record: GameRecord::new(setup, &player.name(), &adversary.name()),
+            agents: [player, adversary],
+        }
+    }
+
+    // Has the agent whose turn it is move, and tells both agents about it. Returns `None` once the
+    // game is over or if the agent gave up, and an error if the agent chose a move that isn't legal,
+    // which isn't made.
+    pub fn step(&mut self) -> Result<Option<Move>, String> {
+        if self.state.is_terminal() {
+            return Ok(None);
+        }
+        let seat = if self.state.players_turn { 0 } else { 1 };
+        let mov = match self.agents[seat].choose(&self.state) {
+            Some(mov) => mov,
+            None => return Ok(None),
+        };
+        if !self.state.generate_moves().contains(&mov) {
+            return Err(format!("{} chose {}, which isn't legal", self.agents[seat].name(), mov));
+        }
+
+        self.state.apply(&mov);
+        self.state.refill_market();
+        self.record.moves.push(mov);
+        for agent in self.agents.iter_mut() {
+            agent.observe(&mov, &self.state);
+        }
+        Ok(Some(mov))
+    }
+
+    // Plays until the game is over, an agent gives up or `max_plies` moves have been made. Stops
+    // with the error of `step` if an agent chooses a move that isn't legal.
+    pub fn play(&mut self, max_plies: usize) -> Result<(), String> {
+        while self.record.moves.len() < max_plies && self.step()?.is_some() {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::TieBreak;
+    use card::Card;
+    use color::Color;
+    use cost::Tokens;
+    use state::Deck;
+
+    #[test]
+    fn scripted_game() {
+        let player = ScriptedAgent::new("first", &["take KBG", "take KBG", "fly"]);
+        let adversary = ScriptedAgent::new("second", &["take RWK", "take RWK"]);
+        let mut game = Game::new(Setup::Seed(1), Box::new(player), Box::new(adversary));
+        assert_eq!(game.play(100), Ok(()));
+        assert_eq!(game.record.moves.len(), 4);
+        assert_eq!(game.record.player, "first");
+        assert_eq!(game.record.replay(), Ok(game.state.clone()));
+        assert_eq!(game.state.player.tokens.total(), 6);
+    }
+
+    #[test]
+    fn baseline_agents() {
+        let state = State::from_seed(2, 3);
+        let mut random = RandomAgent::new("random", 4);
+        let mov = random.choose(&state).unwrap();
+        assert!(state.generate_moves().contains(&mov));
+        assert_eq!(RandomAgent::new("random", 4).choose(&state), Some(mov));
+
+        // Taking tokens scores better than reserving or passing
+        let mut greedy = GreedyAgent::new("greedy", 5);
+        match greedy.choose(&state) {
+            Some(Move::Take { tokens, .. }) => assert_eq!(tokens.total(), 3),
+            mov => panic!("greedy chose {:?}", mov),
+        }
+
+        let mut game = Game::new(Setup::Seed(6), Box::new(GreedyAgent::new("greedy", 7)), Box::new(RandomAgent::new("random", 8)));
+        assert_eq!(game.play(1000), Ok(()));
+        assert!(game.state.is_terminal());
+        assert_eq!(game.record.replay(), Ok(game.state.clone()));
+    }
+
+    #[test]
+    fn search_agents_play_a_game() {
+        // The same game every time
+        let config = SearchConfig {
+            depth: 2,
+            tie_break: TieBreak::First,
+            ..SearchConfig::default()
+        };
+        let mut player = SearchAgent::new("pondering", config.clone(), Weights::default());
+        player.ponder = true;
+        player.solve_endgames = true;
+        let adversary = SearchAgent::new("plain", config, Weights::default());
+        let mut game = Game::new(Setup::Seed(2), Box::new(player), Box::new(adversary));
+        assert_eq!(game.play(200), Ok(()));
+        assert!(game.state.is_terminal());
+        assert_eq!(game.record.replay(), Ok(game.state.clone()));
+    }
+
+    #[test]
+    fn ismcts_agents_play_a_game() {
+        let config = SearchConfig {
+            node_limit: Some(300),
+            ..SearchConfig::default()
+        };
+        let player = IsmctsAgent::new("ismcts", config, Weights::default(), 3);
+        let mut game = Game::new(Setup::Seed(4), Box::new(player), Box::new(RandomAgent::new("random", 5)));
+        assert_eq!(game.play(200), Ok(()));
+        assert_eq!(game.record.replay(), Ok(game.state.clone()));
+    }
+
+    #[test]
+    fn solve_for_either_side() {
+        let card = |points| Card {
+            color: Color::Blue,
+            cost: Tokens::empty(),
+            points,
+        };
+        let mut state = State::new(2);
+        state.player.cards.push(card(12));
+        state.adversary.cards.push(card(13));
+        state.cards1[0] = card(2);
+        state.players_turn = false;
+
+        // Buying the card wins for the adversary
+        let thought = think(&mut state, &WeightedEvaluator::default(), &SearchConfig::default(), true);
+        assert!(thought.info.starts_with("win forced in 1 plies"), "{}", thought.info);
+        assert!(thought.moves.iter().all(|mov| matches!(*mov, Move::Buy { index: 0, deck: Deck::One, .. })));
+    }
+
+    // Passes even when there are other moves
+    struct Passer;
+
+    impl Agent for Passer {
+        fn name(&self) -> String {
+            "passer".to_string()
+        }
+
+        fn choose(&mut self, _state: &State) -> Option<Move> {
+            Some(Move::Pass)
+        }
+    }
+
+    #[test]
+    fn refuse_illegal_moves() {
+        let player = ScriptedAgent::new("first", &["take KBG"]);
+        let mut game = Game::new(Setup::Seed(1), Box::new(player), Box::new(Passer));
+        assert_eq!(game.play(100), Err("passer chose pass, which isn't legal".to_string()));
+        assert_eq!(game.record.moves.len(), 1);
+        assert_eq!(game.record.replay(), Ok(game.state.clone()));
+    }
+}
@@ -1,78 +1,631 @@
 use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
 use algo::state::{State, Score};
+use algo::evaluator::Evaluator;
+use algo::result::{SearchResult, Stats};
+use algo::config::{SearchConfig, Limits};
 
-pub fn alphabeta<S: State>(mut state: &mut S) -> Vec<S::Move> {
-    let possible_moves = state.generate_moves();
+// The side a node is searched for, to index `Search::history`
+const MAX: usize = 0;
+const MIN: usize = 1;
+
+// Move ordering learned during the search. Killers are the last two moves that caused a cutoff
+// at each depth, history counts how often (and how deep) a move caused a cutoff anywhere, for each
+// side apart since a good move for one is rarely good for the other. Without `ordering` moves are
+// searched in the order they were generated, to measure what the ordering is worth.
+struct Search<M> {
+    stats: Stats,
+    limits: Limits,
+    prune: bool,
+    killers: Vec<[Option<M>; 2]>,
+    history: [HashMap<M, u64>; 2],
+    ordering: bool,
+}
+
+impl<M: Copy + Eq + Hash> Search<M> {
+    fn new(depth: usize, limits: Limits, prune: bool) -> Search<M> {
+        Search {
+            stats: Stats::default(),
+            limits,
+            prune,
+            killers: vec![[None, None]; depth + 1],
+            history: [HashMap::new(), HashMap::new()],
+            ordering: true,
+        }
+    }
+
+    // Killers first, then by history. The sort is stable, so the order from `generate_moves` is
+    // kept for moves that never caused a cutoff.
+    fn order(&self, moves: &mut [M], depth: usize, side: usize) {
+        if !self.ordering {
+            return;
+        }
+        let killers = self.killers[depth];
+        moves.sort_by_key(|mov| {
+            if killers[0] == Some(*mov) {
+                (0, 0)
+            } else if killers[1] == Some(*mov) {
+                (1, 0)
+            } else {
+                (2, u64::MAX - self.history[side].get(mov).cloned().unwrap_or(0))
+            }
+        });
+    }
+
+    fn cutoff(&mut self, mov: M, depth: usize, side: usize) {
+        self.stats.cutoffs += 1;
+        let killers = &mut self.killers[depth];
+        if killers[0] != Some(mov) {
+            killers[1] = killers[0];
+            killers[0] = Some(mov);
+        }
+        *self.history[side].entry(mov).or_insert(0) += (depth * depth) as u64;
+    }
+}
+
+// Hands out the moves of a node a batch at a time: first the killers that are legal here, then
+// every stage in turn without the killers, ordered by history. A stage is only generated once it's
+// needed, or to check a killer.
+struct Batches<S: State> {
+    stages: Vec<Option<Vec<S::Move>>>,
+    // What the state found generating the stages so far
+    found: S::Stages,
+    killers: Vec<S::Move>,
+    // The next stage to hand out, `None` before the killers
+    next: Option<usize>,
+}
+
+impl<S: State> Batches<S> {
+    fn new() -> Batches<S> {
+        Batches {
+            stages: Vec::new(),
+            found: S::Stages::default(),
+            killers: Vec::with_capacity(2),
+            next: None,
+        }
+    }
+
+    fn stage(&mut self, state: &S, search: &Search<S::Move>, stage: usize) -> Option<Vec<S::Move>> {
+        match self.stages.get_mut(stage).and_then(|moves| moves.take()) {
+            Some(moves) => Some(moves),
+            None => state.generate_stage(stage, &mut self.found).map(|mut moves| {
+                if search.prune {
+                    state.prune(&mut moves);
+                }
+                moves
+            }),
+        }
+    }
+
+    fn next(&mut self, state: &S, search: &Search<S::Move>, depth: usize, side: usize) -> Option<Vec<S::Move>> {
+        let stage = match self.next {
+            Some(stage) => stage,
+            None => {
+                self.next = Some(0);
+                let killers = if search.ordering { search.killers[depth] } else { [None, None] };
+                for killer in killers.iter().filter_map(|killer| *killer) {
+                    let stage = state.stage_of(&killer);
+                    if let Some(moves) = self.stage(state, search, stage) {
+                        if moves.contains(&killer) {
+                            self.killers.push(killer);
+                        }
+                        if self.stages.len() <= stage {
+                            self.stages.resize(stage + 1, None);
+                        }
+                        self.stages[stage] = Some(moves);
+                    }
+                }
+                if !self.killers.is_empty() {
+                    return Some(self.killers.clone());
+                }
+                0
+            },
+        };
+
+        let mut moves = self.stage(state, search, stage)?;
+        self.next = Some(stage + 1);
+        moves.retain(|mov| !self.killers.contains(mov));
+        search.order(&mut moves, depth, side);
+        Some(moves)
+    }
+}
+
+// Searches with iterative deepening, so the move ordering and the score of one iteration can be
+// used by the next. Every iteration starts with an aspiration window around the previous score and
+// only widens it when the result falls outside of it. An iteration that runs into a limit is thrown
+// away, except for the first one which only scores the root moves and is exact either way.
+pub fn alphabeta<S: State, E: Evaluator<S>>(state: &mut S, evaluator: &E, config: &SearchConfig) -> SearchResult<S::Move, S::Score> {
+    deepen(state, evaluator, config, true, |_| ())
+}
+
+// Like `alphabeta`, calling `report` with the result of every iteration that is kept, as soon as
+// it's done
+pub fn alphabeta_reporting<S, E, R>(state: &mut S, evaluator: &E, config: &SearchConfig, report: R) -> SearchResult<S::Move, S::Score> where S: State, E: Evaluator<S>, R: FnMut(&SearchResult<S::Move, S::Score>) {
+    deepen(state, evaluator, config, true, report)
+}
+
+fn deepen<S, E, R>(state: &mut S, evaluator: &E, config: &SearchConfig, ordering: bool, mut report: R) -> SearchResult<S::Move, S::Score> where S: State, E: Evaluator<S>, R: FnMut(&SearchResult<S::Move, S::Score>) {
+    let start = Instant::now();
+    let max_depth = config.depth.saturating_sub(1);
+    let mut search = Search::new(max_depth, Limits::new(config, start), config.prune);
+    search.ordering = ordering;
+    let mut possible_moves = state.generate_moves();
+    if config.prune {
+        state.prune(&mut possible_moves);
+    }
+    let mut best_moves: Vec<S::Move> = Vec::new();
+    let mut best_score = S::Score::min_value();
+    let mut pv = Vec::new();
+    // The score swings between odd and even depths depending on who moves last, so the window is
+    // centered on the score of the iteration two plies shallower
+    let mut scores: Vec<S::Score> = Vec::with_capacity(max_depth + 1);
+
+    for depth in 0..max_depth + 1 {
+        let mut window = evaluator.aspiration_window();
+        loop {
+            let (alpha, beta) = match window {
+                Some(window) if depth >= 2 => (scores[depth - 2].offset(-window), scores[depth - 2].offset(window)),
+                _ => (S::Score::min_value(), S::Score::max_value()),
+            };
+            let (score, moves, line) = root(state, &mut search, evaluator, &possible_moves, depth, alpha, beta);
+            if search.limits.reached && depth > 0 {
+                break;
+            }
+
+            // Outside of the window the score is only a bound, so search again with a wider one
+            if (score <= alpha && alpha > S::Score::min_value()) || (score >= beta && beta < S::Score::max_value()) {
+                window = window.map(|window| window.saturating_mul(4));
+                continue;
+            }
+
+            best_score = score;
+            best_moves = moves;
+            pv = line;
+            scores.push(score);
+            report(&result(state, &search, &best_moves, best_score, &pv, scores.len(), start));
+            break;
+        }
+        if search.limits.reached {
+            break;
+        }
+
+        // Search the best moves first in the next iteration
+        possible_moves.sort_by_key(|mov| !best_moves.contains(mov));
+    }
+
+    result(state, &search, &best_moves, best_score, &pv, scores.len(), start)
+}
+
+// The move the principal variation starts with stays first, the others are put back in the order
+// `generate_moves` returned them in
+fn result<S: State>(state: &S, search: &Search<S::Move>, best_moves: &[S::Move], score: S::Score, pv: &[S::Move], depth: usize, start: Instant) -> SearchResult<S::Move, S::Score> {
+    let mut moves: Vec<S::Move> = best_moves.first().cloned().into_iter().collect();
+    moves.extend(state.generate_moves().into_iter().filter(|mov| best_moves.iter().skip(1).any(|best| best == mov)));
+
+    SearchResult {
+        moves,
+        score,
+        pv: pv.to_vec(),
+        nodes: search.stats.nodes,
+        depth,
+        cutoffs: search.stats.cutoffs,
+        elapsed: start.elapsed(),
+    }
+}
+
+// Finds every root move with the best score, the first of them being the one the principal
+// variation starts with. After the first move the others are only tested against a null window just
+// below the best score, and only the ones that reach it are searched again to get their exact
+// score, so a move only counts as a tie if it really scores the same.
+fn root<S: State, E: Evaluator<S>>(state: &mut S, search: &mut Search<S::Move>, evaluator: &E, possible_moves: &[S::Move], depth: usize, alpha: S::Score, beta: S::Score) -> (S::Score, Vec<S::Move>, Vec<S::Move>) {
     let mut best_moves: Vec<S::Move> = Vec::with_capacity(5);
     let mut best_score = S::Score::min_value();
+    let mut pv = Vec::new();
 
-    for mov in possible_moves.into_iter() {
-        state.apply(&mov);
-        let score = min::<S>(&mut state, 5, best_score, S::Score::max_value());
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        let score = if best_moves.is_empty() {
+            min(state, search, evaluator, depth, alpha, beta, &mut line)
+        } else {
+            let score = min(state, search, evaluator, depth, best_score.offset(-1), best_score, &mut line);
+            if score >= best_score && score < beta {
+                line.clear();
+                min(state, search, evaluator, depth, best_score.offset(-1), beta, &mut line)
+            } else {
+                score
+            }
+        };
+        state.undo(mov);
 
         if score == best_score {
-            best_moves.push(mov);
+            best_moves.push(*mov);
         } else if score > best_score {
             best_score = score;
             best_moves.clear();
-            best_moves.push(mov);
+            best_moves.push(*mov);
+            pv.clear();
+            pv.push(*mov);
+            pv.append(&mut line);
+        }
+        if search.limits.reached && depth > 0 {
+            break;
         }
-        state.undo(&mov);
     }
 
-    best_moves
+    (best_score, best_moves, pv)
 }
 
-fn min<S: State>(mut state: &mut S, depth: usize, alpha: S::Score, mut beta: S::Score) -> S::Score {
-    if depth == 0 || state.is_terminal() {
-        return state.score();
+// Splits the root moves between `threads` workers that each search a copy of the state. The best
+// score found so far is shared so every worker can use it as its alpha bound, just below it so ties
+// are exact and the best moves are the same as `alphabeta`'s. There are no iterations to fall back
+// on, so when a limit is reached a worker drops the move it was searching, unless it hasn't
+// finished any other. The node limit is split evenly between the workers.
+pub fn parallel_alphabeta<S, E>(state: &S, evaluator: &E, config: &SearchConfig) -> SearchResult<S::Move, S::Score> where S: State + Clone + Send + Sync, S::Move: Send + Sync, S::Score: Send, E: Evaluator<S> + Sync {
+    let start = Instant::now();
+    let max_depth = config.depth.saturating_sub(1);
+    let mut possible_moves = state.generate_moves();
+    if config.prune {
+        state.prune(&mut possible_moves);
     }
+    let threads = cmp::max(1, cmp::min(config.threads, possible_moves.len()));
+    let worker_config = SearchConfig {
+        node_limit: config.node_limit.map(|limit| limit / threads as u64),
+        ..config.clone()
+    };
+    let best_score = Mutex::new(S::Score::min_value());
+
+    let (mut scored_moves, stats) = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|worker| {
+            let possible_moves = &possible_moves;
+            let best_score = &best_score;
+            let worker_config = &worker_config;
+            scope.spawn(move || {
+                let mut state = state.clone();
+                let mut search = Search::new(max_depth, Limits::new(worker_config, start), config.prune);
+                let mut scored_moves = Vec::new();
+                for (i, mov) in possible_moves.iter().enumerate().skip(worker).step_by(threads) {
+                    let mut line = vec![*mov];
+                    // Just below the best score, so a move that ties it gets its exact score
+                    let alpha = best_score.lock().unwrap().offset(-1);
+                    state.apply(mov);
+                    let score = min(&mut state, &mut search, evaluator, max_depth, alpha, S::Score::max_value(), &mut line);
+                    state.undo(mov);
 
-    let possible_moves = state.generate_moves();
-    if possible_moves.is_empty() {
-        return state.score();
+                    if search.limits.reached && !scored_moves.is_empty() {
+                        break;
+                    }
+                    let mut best_score = best_score.lock().unwrap();
+                    *best_score = cmp::max(*best_score, score);
+                    scored_moves.push((i, score, line));
+                    if search.limits.reached {
+                        break;
+                    }
+                }
+                (scored_moves, search.stats)
+            })
+        }).collect();
+
+        let mut scored_moves: Vec<(usize, S::Score, Vec<S::Move>)> = Vec::with_capacity(possible_moves.len());
+        let mut stats = Stats::default();
+        for worker in workers.into_iter() {
+            let (mut worker_moves, worker_stats) = worker.join().unwrap();
+            scored_moves.append(&mut worker_moves);
+            stats.nodes += worker_stats.nodes;
+            stats.cutoffs += worker_stats.cutoffs;
+        }
+        (scored_moves, stats)
+    });
+
+    // Back in the order `generate_moves` returned them in, the principal variation being that of
+    // the first best move
+    scored_moves.sort_by_key(|&(i, _, _)| i);
+    let best_score = best_score.into_inner().unwrap();
+    let mut best_lines = scored_moves.into_iter()
+        .filter(|&(_, score, _)| score == best_score)
+        .map(|(_, _, line)| line);
+    let pv = best_lines.next().unwrap_or_default();
+    let mut moves: Vec<S::Move> = pv.first().cloned().into_iter().collect();
+    moves.extend(best_lines.map(|line| line[0]));
+
+    SearchResult {
+        moves,
+        score: best_score,
+        pv,
+        nodes: stats.nodes,
+        depth: max_depth + 1,
+        cutoffs: stats.cutoffs,
+        elapsed: start.elapsed(),
     }
+}
+
+// `pv` is extended with the best line found below this node
+fn min<S: State, E: Evaluator<S>>(state: &mut S, search: &mut Search<S::Move>, evaluator: &E, depth: usize, alpha: S::Score, mut beta: S::Score, pv: &mut Vec<S::Move>) -> S::Score {
+    search.stats.nodes += 1;
+    if search.limits.check(search.stats.nodes) || depth == 0 || state.is_terminal() {
+        return evaluator.evaluate(state);
+    }
+
     let mut worst_score = S::Score::max_value();
+    let mut best_line = Vec::new();
+    let mut searched = 0;
+    let mut batches = Batches::new();
 
-    for mov in possible_moves.iter() {
-        state.apply(mov);
-        let score = max::<S>(&mut state, depth - 1, alpha, beta);
-        state.undo(mov);
+    // The next batch is only generated if none of the moves so far caused a cutoff
+    'batches: while let Some(possible_moves) = batches.next(state, search, depth, MIN) {
 
-        worst_score = cmp::min(worst_score, score);
-        beta = cmp::min(beta, score);
+        for mov in possible_moves.iter() {
+            let mut line = Vec::new();
+            state.apply(mov);
+            let score = if searched == 0 {
+                max(state, search, evaluator, depth - 1, alpha, beta, &mut line)
+            } else {
+                // Principal variation search, prove this move is no better with a null window
+                let score = max(state, search, evaluator, depth - 1, beta.offset(-1), beta, &mut line);
+                if alpha < score && score < beta {
+                    line.clear();
+                    max(state, search, evaluator, depth - 1, alpha, score, &mut line)
+                } else {
+                    score
+                }
+            };
+            state.undo(mov);
+            searched += 1;
 
-        if beta <= alpha {
-            break;
+            if score < worst_score {
+                worst_score = score;
+                best_line.clear();
+                best_line.push(*mov);
+                best_line.append(&mut line);
+            }
+            beta = cmp::min(beta, score);
+
+            if beta <= alpha {
+                search.cutoff(*mov, depth, MIN);
+                break 'batches;
+            }
+            if search.limits.reached {
+                break 'batches;
+            }
         }
     }
-    
+
+    if searched == 0 {
+        return evaluator.evaluate(state);
+    }
+
+    pv.append(&mut best_line);
     worst_score
 }
 
-fn max<S: State>(mut state: &mut S, depth: usize, mut alpha: S::Score, beta: S::Score) -> S::Score {
-    if depth == 0 || state.is_terminal() {
-        return state.score();
+fn max<S: State, E: Evaluator<S>>(state: &mut S, search: &mut Search<S::Move>, evaluator: &E, depth: usize, mut alpha: S::Score, beta: S::Score, pv: &mut Vec<S::Move>) -> S::Score {
+    search.stats.nodes += 1;
+    if search.limits.check(search.stats.nodes) || depth == 0 || state.is_terminal() {
+        return evaluator.evaluate(state);
     }
 
-    let possible_moves = state.generate_moves();
-    if possible_moves.is_empty() {
-        return state.score();
-    }
     let mut best_score = S::Score::min_value();
+    let mut best_line = Vec::new();
+    let mut searched = 0;
+    let mut batches = Batches::new();
 
-    for mov in possible_moves.iter() {
-        state.apply(mov);
-        let score = min::<S>(&mut state, depth - 1, alpha, beta);
-        state.undo(mov);
+    // The next batch is only generated if none of the moves so far caused a cutoff
+    'batches: while let Some(possible_moves) = batches.next(state, search, depth, MAX) {
 
-        best_score = cmp::max(best_score, score);
-        alpha = cmp::max(alpha, score);
+        for mov in possible_moves.iter() {
+            let mut line = Vec::new();
+            state.apply(mov);
+            let score = if searched == 0 {
+                min(state, search, evaluator, depth - 1, alpha, beta, &mut line)
+            } else {
+                // Principal variation search, prove this move is no better with a null window
+                let score = min(state, search, evaluator, depth - 1, alpha, alpha.offset(1), &mut line);
+                if alpha < score && score < beta {
+                    line.clear();
+                    min(state, search, evaluator, depth - 1, score, beta, &mut line)
+                } else {
+                    score
+                }
+            };
+            state.undo(mov);
+            searched += 1;
 
-        if beta <= alpha {
-            break;
+            if score > best_score {
+                best_score = score;
+                best_line.clear();
+                best_line.push(*mov);
+                best_line.append(&mut line);
+            }
+            alpha = cmp::max(alpha, score);
+
+            if beta <= alpha {
+                search.cutoff(*mov, depth, MAX);
+                break 'batches;
+            }
+            if search.limits.reached {
+                break 'batches;
+            }
         }
     }
 
+    if searched == 0 {
+        return evaluator.evaluate(state);
+    }
+
+    pv.append(&mut best_line);
     best_score
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::evaluator::ScoreEvaluator;
+    use algo::minimax::minimax;
+
+    // Three moves a ply, six plies deep, with the leaves scored from a hash of the path. There are
+    // only a few different scores, so there are ties. `swing` is added to the score at every depth,
+    // to push iterations out of their aspiration window.
+    struct Tree {
+        path: Vec<u8>,
+        swing: fn(usize) -> i64,
+    }
+
+    impl Tree {
+        fn new(swing: fn(usize) -> i64) -> Tree {
+            Tree {
+                path: Vec::new(),
+                swing,
+            }
+        }
+    }
+
+    impl State for Tree {
+        type Score = i64;
+        type Move = u8;
+        type Stages = ();
+
+        fn score(&self) -> i64 {
+            let hash = self.path.iter().fold(17, |hash, &mov| (hash * 31 + mov as i64 + 1) % 1009);
+            (hash % 5) * 100 + (self.swing)(self.path.len())
+        }
+
+        fn generate_moves(&self) -> Vec<u8> {
+            if self.is_terminal() { Vec::new() } else { vec![0, 1, 2] }
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.path.len() == 6
+        }
+
+        fn apply(&mut self, mov: &u8) {
+            self.path.push(*mov);
+        }
+
+        fn undo(&mut self, _mov: &u8) {
+            self.path.pop();
+        }
+    }
+
+    fn sorted(moves: &[u8]) -> Vec<u8> {
+        let mut moves = moves.to_vec();
+        moves.sort();
+        moves
+    }
+
+    // Scores like the tree itself, with aspiration windows
+    const WINDOW: i64 = 3000;
+
+    struct Windowed;
+
+    impl Evaluator<Tree> for Windowed {
+        fn evaluate(&self, tree: &Tree) -> i64 {
+            tree.score()
+        }
+
+        fn aspiration_window(&self) -> Option<i64> {
+            Some(WINDOW)
+        }
+    }
+
+    fn search_both(tree: &mut Tree, depth: usize) -> (SearchResult<u8, i64>, SearchResult<u8, i64>) {
+        let config = SearchConfig {
+            depth,
+            ..SearchConfig::default()
+        };
+        (alphabeta(tree, &Windowed, &config), minimax(tree, &ScoreEvaluator, &config))
+    }
+
+    #[test]
+    fn same_result_as_minimax() {
+        let mut tree = Tree::new(|_| 0);
+        for depth in 1..7 {
+            let (result, expected) = search_both(&mut tree, depth);
+            assert_eq!(result.score, expected.score);
+            assert_eq!(sorted(&result.moves), sorted(&expected.moves));
+            assert_eq!(result.pv.first(), result.moves.first());
+            assert_eq!(result.pv.len(), depth);
+        }
+    }
+
+    #[test]
+    fn search_again_outside_the_window() {
+        // Up at the third ply, down at the fifth
+        let mut tree = Tree::new(|plies| match plies {
+            0..=2 => 0,
+            3 | 4 => 20000,
+            _ => -20000,
+        });
+        let scores: Vec<i64> = (1..7).map(|depth| search_both(&mut tree, depth).1.score).collect();
+        assert!(scores[2] > scores[0] + WINDOW);
+        assert!(scores[4] < scores[2] - WINDOW);
+
+        for depth in 3..7 {
+            let (result, expected) = search_both(&mut tree, depth);
+            assert_eq!(result.score, expected.score);
+            assert_eq!(sorted(&result.moves), sorted(&expected.moves));
+            assert_eq!(result.pv.first(), result.moves.first());
+        }
+    }
+
+    #[test]
+    fn report_every_iteration() {
+        let mut tree = Tree::new(|_| 0);
+        let config = SearchConfig {
+            depth: 4,
+            ..SearchConfig::default()
+        };
+        let mut reported = Vec::new();
+        let result = alphabeta_reporting(&mut tree, &ScoreEvaluator, &config, |result| {
+            reported.push((result.depth, result.score, result.moves.clone()));
+        });
+        let depths: Vec<usize> = reported.iter().map(|&(depth, _, _)| depth).collect();
+        assert_eq!(depths, vec![1, 2, 3, 4]);
+        assert_eq!(reported.pop(), Some((result.depth, result.score, result.moves)));
+        assert_eq!(reported[0].1, search_both(&mut tree, 1).1.score);
+    }
+
+    #[test]
+    fn order_moves_by_killers_and_history() {
+        let mut search: Search<u8> = Search::new(2, Limits::new(&SearchConfig::default(), Instant::now()), false);
+        search.cutoff(4, 1, MAX);
+        search.cutoff(4, 2, MAX);
+        search.cutoff(3, 2, MAX);
+        search.cutoff(5, 1, MAX);
+
+        let mut moves = vec![1, 2, 3, 4, 5];
+        search.order(&mut moves, 2, MAX);
+        assert_eq!(moves, vec![3, 4, 5, 1, 2]);
+
+        let mut moves = vec![1, 2, 3, 4, 5];
+        search.order(&mut moves, 1, MAX);
+        assert_eq!(moves, vec![5, 4, 3, 1, 2]);
+
+        // The other side keeps its own history
+        search.cutoff(1, 2, MIN);
+        let mut moves = vec![1, 2, 3, 4, 5];
+        search.order(&mut moves, 1, MAX);
+        assert_eq!(moves, vec![5, 4, 3, 1, 2]);
+
+        let mut moves = vec![1, 2, 3, 4, 5];
+        search.order(&mut moves, 1, MIN);
+        assert_eq!(moves, vec![5, 4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn ordering_saves_nodes() {
+        let mut state = ::state::State::from_seed(2, 7);
+        let config = SearchConfig {
+            depth: 4,
+            ..SearchConfig::default()
+        };
+        let evaluator = ::eval::WeightedEvaluator::default();
+        let ordered = deepen(&mut state, &evaluator, &config, true, |_| ());
+        let unordered = deepen(&mut state, &evaluator, &config, false, |_| ());
+        assert_eq!(ordered.score, unordered.score);
+        assert!(ordered.nodes < unordered.nodes, "{} nodes ordered, {} unordered", ordered.nodes, unordered.nodes);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use rand::Rng;
+use algo::state::State;
+use algo::evaluator::Evaluator;
+use algo::result::SearchResult;
+use algo::minimax::minimax;
+use algo::alphabeta::{alphabeta_reporting, parallel_alphabeta};
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Algorithm {
+    Minimax,
+    AlphaBeta,
+    // Alphabeta with the root moves split between `SearchConfig::threads` threads
+    Parallel,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Algorithm::Minimax => write!(f, "minimax"),
+            Algorithm::AlphaBeta => write!(f, "alphabeta"),
+            Algorithm::Parallel => write!(f, "parallel"),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Algorithm, String> {
+        match s {
+            "minimax" => Ok(Algorithm::Minimax),
+            "alphabeta" => Ok(Algorithm::AlphaBeta),
+            "parallel" => Ok(Algorithm::Parallel),
+            _ => Err(format!("unknown algorithm {:?}, expected minimax, alphabeta or parallel", s)),
+        }
+    }
+}
+
+// Which of the moves that share the best score gets played
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum TieBreak {
+    // The first one, which the principal variation starts with, so games can be reproduced
+    First,
+    Random,
+}
+
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TieBreak::First => write!(f, "first"),
+            TieBreak::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TieBreak, String> {
+        match s {
+            "first" => Ok(TieBreak::First),
+            "random" => Ok(TieBreak::Random),
+            _ => Err(format!("unknown tie break {:?}, expected first or random", s)),
+        }
+    }
+}
+
+impl TieBreak {
+    pub fn pick<'a, M, R: Rng>(&self, moves: &'a [M], rng: &mut R) -> Option<&'a M> {
+        match *self {
+            TieBreak::First => moves.first(),
+            TieBreak::Random => rng.choose(moves),
+        }
+    }
+}
+
+// Shared between a search and whoever may want to interrupt it, like a UI when the user resigns.
+// Clones share the same flag.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct Stop(Arc<AtomicBool>);
+
+impl Stop {
+    pub fn new() -> Stop {
+        Stop::default()
+    }
+
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Two flags are only equal if stopping one stops the other
+impl PartialEq for Stop {
+    fn eq(&self, other: &Stop) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct SearchConfig {
+    pub algorithm: Algorithm,
+    // Plies to search, counting the root move like `SearchResult::depth`
+    pub depth: usize,
+    // Once a limit is reached the search returns the best moves found so far
+    pub time_limit: Option<Duration>,
+    pub node_limit: Option<u64>,
+    pub tie_break: TieBreak,
+    // Only used by `Algorithm::Parallel`
+    pub threads: usize,
+    // Leave out the moves `State::prune` finds no better than others, in minimax and alphabeta
+    pub prune: bool,
+    // Stopping it has the same effect as reaching a limit
+    pub stop: Stop,
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            algorithm: Algorithm::AlphaBeta,
+            depth: 6,
+            time_limit: None,
+            node_limit: None,
+            tie_break: TieBreak::Random,
+            threads: 4,
+            prune: false,
+            stop: Stop::new(),
+        }
+    }
+}
+
+impl SearchConfig {
+    // The command line options understood by `set`, for usage messages
+    pub const USAGE: &'static str = "[--algorithm minimax|alphabeta|parallel] [--depth PLIES] [--time MS] [--nodes N] [--tie-break first|random] [--threads N] [--prune true|false]";
+
+    // Sets the option called `name`, with or without its leading dashes. Returns `Ok(false)` if it
+    // isn't a search option, so binaries can mix these with their own.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<bool, String> {
+        fn number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+            value.parse().map_err(|_| format!("invalid {} {:?}", name, value))
+        }
+
+        let name = name.trim_start_matches('-');
+        match name {
+            "algorithm" => self.algorithm = value.parse()?,
+            "depth" => {
+                self.depth = number(name, value)?;
+                if self.depth == 0 {
+                    return Err("depth must be at least 1".to_string());
+                }
+            },
+            "time" => self.time_limit = Some(Duration::from_millis(number(name, value)?)),
+            "nodes" => self.node_limit = Some(number(name, value)?),
+            "tie-break" => self.tie_break = value.parse()?,
+            "threads" => self.threads = number(name, value)?,
+            "prune" => self.prune = number(name, value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for SearchConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "--algorithm {} --depth {}", self.algorithm, self.depth)?;
+        if let Some(time_limit) = self.time_limit {
+            write!(f, " --time {}", time_limit.as_millis())?;
+        }
+        if let Some(node_limit) = self.node_limit {
+            write!(f, " --nodes {}", node_limit)?;
+        }
+        write!(f, " --tie-break {} --threads {}", self.tie_break, self.threads)?;
+        if self.prune {
+            write!(f, " --prune true")?;
+        }
+        Ok(())
+    }
+}
+
+// Searches with the algorithm from `config`
+pub fn search<S, E>(state: &mut S, evaluator: &E, config: &SearchConfig) -> SearchResult<S::Move, S::Score> where S: State + Clone + Send + Sync, S::Move: Send + Sync, S::Score: Send, E: Evaluator<S> + Sync {
+    search_reporting(state, evaluator, config, |_| ())
+}
+
+// Like `search`, calling `report` with the result of every depth as soon as it's searched. Only
+// alphabeta searches one depth after another, the other algorithms report their result once.
+pub fn search_reporting<S, E, R>(state: &mut S, evaluator: &E, config: &SearchConfig, mut report: R) -> SearchResult<S::Move, S::Score> where S: State + Clone + Send + Sync, S::Move: Send + Sync, S::Score: Send, E: Evaluator<S> + Sync, R: FnMut(&SearchResult<S::Move, S::Score>) {
+    let result = match config.algorithm {
+        Algorithm::Minimax => minimax(state, evaluator, config),
+        Algorithm::AlphaBeta => return alphabeta_reporting(state, evaluator, config, report),
+        Algorithm::Parallel => parallel_alphabeta(state, evaluator, config),
+    };
+    report(&result);
+    result
+}
+
+// Tells a search when it has used up the time or nodes it was given, or has been stopped. Once a
+// limit is reached it stays reached, so the search can unwind without checking again.
+pub(crate) struct Limits {
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stop: Stop,
+    pub reached: bool,
+}
+
+impl Limits {
+    pub fn new(config: &SearchConfig, start: Instant) -> Limits {
+        Limits {
+            deadline: config.time_limit.map(|time_limit| start + time_limit),
+            node_limit: config.node_limit,
+            stop: config.stop.clone(),
+            reached: false,
+        }
+    }
+
+    // Called for every node with the number of nodes searched so far. The clock is only read
+    // every so often because it's slow compared to searching a node.
+    pub fn check(&mut self, nodes: u64) -> bool {
+        if !self.reached {
+            self.reached = self.stop.is_stopped() ||
+                self.node_limit.is_some_and(|limit| nodes >= limit) ||
+                (nodes.is_multiple_of(256) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline));
+        }
+        self.reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_options() {
+        let mut config = SearchConfig::default();
+        assert_eq!(config.set("--algorithm", "minimax"), Ok(true));
+        assert_eq!(config.set("depth", "3"), Ok(true));
+        assert_eq!(config.set("--time", "1500"), Ok(true));
+        assert_eq!(config.set("--tie-break", "first"), Ok(true));
+        assert_eq!(config.set("--prune", "true"), Ok(true));
+        assert_eq!(config.set("--games", "10"), Ok(false));
+        assert_eq!(config, SearchConfig {
+            algorithm: Algorithm::Minimax,
+            depth: 3,
+            time_limit: Some(Duration::from_millis(1500)),
+            tie_break: TieBreak::First,
+            prune: true,
+            stop: config.stop.clone(),
+            ..SearchConfig::default()
+        });
+        assert_eq!(config.to_string(), "--algorithm minimax --depth 3 --time 1500 --tie-break first --threads 4 --prune true");
+
+        assert!(config.set("--algorithm", "negamax").is_err());
+        assert!(config.set("--depth", "0").is_err());
+        assert!(config.set("--nodes", "many").is_err());
+        assert!(config.set("--prune", "yes").is_err());
+    }
+}
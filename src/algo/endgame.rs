@@ -0,0 +1,160 @@
+use std::cmp;
+use std::time::Instant;
+use algo::state::{Endgame, Outcome};
+use algo::result::{SearchResult, Stats};
+use algo::config::{SearchConfig, Limits};
+
+struct Search {
+    stats: Stats,
+    limits: Limits,
+}
+
+// Searches for a forced result, going one ply deeper at a time until one is proven or `max_depth`
+// plies have been searched. Positions at the horizon are scored as a loss in one search and as a
+// win in another, the result is proven once both agree. Returns `None` if the result couldn't be
+// proven before `max_depth` or one of the limits of `config` was reached, the moves are the ones
+// that force the result. Only what `apply` does is searched, so in a game where chance takes a
+// hand between moves the result is only forced if chance doesn't change it.
+pub fn endgame<S: Endgame>(state: &mut S, max_depth: usize, config: &SearchConfig) -> Option<SearchResult<S::Move, Outcome>> {
+    let start = Instant::now();
+    let mut search = Search {
+        stats: Stats::default(),
+        limits: Limits::new(config, start),
+    };
+    let possible_moves = state.generate_moves();
+
+    for depth in 0..max_depth {
+        let (pessimistic, moves, pv) = root(state, &mut search, &possible_moves, depth, Outcome::Loss);
+        let (optimistic, _, _) = root(state, &mut search, &possible_moves, depth, Outcome::Win);
+        if search.limits.reached {
+            return None;
+        }
+
+        if pessimistic == optimistic {
+            return Some(SearchResult {
+                moves,
+                score: pessimistic,
+                pv,
+                nodes: search.stats.nodes,
+                depth: depth + 1,
+                cutoffs: search.stats.cutoffs,
+                elapsed: start.elapsed(),
+            });
+        }
+    }
+
+    None
+}
+
+fn root<S: Endgame>(state: &mut S, search: &mut Search, possible_moves: &[S::Move], depth: usize, horizon: Outcome) -> (Outcome, Vec<S::Move>, Vec<S::Move>) {
+    let mut best_moves = Vec::new();
+    let mut best_outcome = Outcome::Loss;
+    let mut pv = Vec::new();
+
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        // Searching just below the best outcome, so a move that ties with it is exact
+        let alpha = match best_outcome {
+            Outcome::Win => Outcome::Draw,
+            _ => Outcome::Loss,
+        };
+        let outcome = min(state, search, depth, horizon, alpha, Outcome::Win, &mut line);
+        state.undo(mov);
+
+        // Only moves that reach the best outcome against every defence are kept
+        if outcome > best_outcome || best_moves.is_empty() {
+            best_outcome = outcome;
+            best_moves.clear();
+            pv.clear();
+            pv.push(*mov);
+            pv.append(&mut line);
+        }
+        if outcome == best_outcome {
+            best_moves.push(*mov);
+        }
+    }
+
+    (best_outcome, best_moves, pv)
+}
+
+fn min<S: Endgame>(state: &mut S, search: &mut Search, depth: usize, horizon: Outcome, alpha: Outcome, mut beta: Outcome, pv: &mut Vec<S::Move>) -> Outcome {
+    search.stats.nodes += 1;
+    if let Some(outcome) = state.outcome() {
+        return outcome;
+    }
+    // What is returned once a limit is reached doesn't matter, the search is thrown away
+    if search.limits.check(search.stats.nodes) || depth == 0 {
+        return horizon;
+    }
+
+    let possible_moves = state.generate_moves();
+    if possible_moves.is_empty() {
+        return horizon;
+    }
+    let mut worst_outcome = Outcome::Win;
+    let mut best_line = Vec::new();
+
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        let outcome = max(state, search, depth - 1, horizon, alpha, beta, &mut line);
+        state.undo(mov);
+
+        if outcome < worst_outcome || best_line.is_empty() {
+            worst_outcome = outcome;
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        beta = cmp::min(beta, outcome);
+
+        if beta <= alpha {
+            search.stats.cutoffs += 1;
+            break;
+        }
+    }
+
+    pv.append(&mut best_line);
+    worst_outcome
+}
+
+fn max<S: Endgame>(state: &mut S, search: &mut Search, depth: usize, horizon: Outcome, mut alpha: Outcome, beta: Outcome, pv: &mut Vec<S::Move>) -> Outcome {
+    search.stats.nodes += 1;
+    if let Some(outcome) = state.outcome() {
+        return outcome;
+    }
+    if search.limits.check(search.stats.nodes) || depth == 0 {
+        return horizon;
+    }
+
+    let possible_moves = state.generate_moves();
+    if possible_moves.is_empty() {
+        return horizon;
+    }
+    let mut best_outcome = Outcome::Loss;
+    let mut best_line = Vec::new();
+
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        let outcome = min(state, search, depth - 1, horizon, alpha, beta, &mut line);
+        state.undo(mov);
+
+        if outcome > best_outcome || best_line.is_empty() {
+            best_outcome = outcome;
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        alpha = cmp::max(alpha, outcome);
+
+        if beta <= alpha {
+            search.stats.cutoffs += 1;
+            break;
+        }
+    }
+
+    pv.append(&mut best_line);
+    best_outcome
+}
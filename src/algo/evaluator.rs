@@ -0,0 +1,42 @@
+use algo::state::{State, Score};
+
+// Scores a position from the perspective of the maximizing player, like `State::score`. The
+// searches take one of these so evaluation ideas can be tried without touching the state.
+pub trait Evaluator<S: State> {
+    fn evaluate(&self, state: &S) -> S::Score;
+    // Half the width of the first aspiration window of `alphabeta`, a difference in score worth
+    // searching again for. Without one every iteration is searched with a full window.
+    fn aspiration_window(&self) -> Option<i64> {
+        None
+    }
+}
+
+// Uses the state's own `score`
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+pub struct ScoreEvaluator;
+
+impl<S: State> Evaluator<S> for ScoreEvaluator {
+    fn evaluate(&self, state: &S) -> S::Score {
+        state.score()
+    }
+}
+
+// Scores from the perspective of the other player, for searching on behalf of the minimizing side
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+pub struct Negated<E>(pub E);
+
+impl<S: State, E: Evaluator<S>> Evaluator<S> for Negated<E> {
+    fn evaluate(&self, state: &S) -> S::Score {
+        self.0.evaluate(state).neg()
+    }
+
+    fn aspiration_window(&self) -> Option<i64> {
+        self.0.aspiration_window()
+    }
+}
@@ -0,0 +1,172 @@
+use std::cmp;
+use std::cmp::Ordering;
+use std::time::Instant;
+use rand::Rng;
+use algo::state::{Score, Determinize};
+use algo::evaluator::Evaluator;
+use algo::config::{SearchConfig, Limits};
+use algo::result::SearchResult;
+
+// How far a playout goes before the score decides who is ahead
+const PLAYOUT_DEPTH: usize = 20;
+const EXPLORATION: f64 = 0.7;
+// How many iterations are run without a time or node limit
+pub const ISMCTS_ITERATIONS: u64 = 10000;
+
+struct Node<K> {
+    // The key of the move that leads here
+    key: Option<K>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Rewards are from the perspective of the player that made `mov`
+    reward: f64,
+    visits: u32,
+    // How often this node's move was legal when its parent was visited
+    availability: u32,
+}
+
+impl<K> Node<K> {
+    fn new(key: Option<K>, parent: Option<usize>) -> Node<K> {
+        Node {
+            key,
+            parent,
+            children: Vec::new(),
+            reward: 0.0,
+            visits: 0,
+            availability: 1,
+        }
+    }
+
+    fn ucb(&self) -> f64 {
+        let visits = self.visits as f64;
+        self.reward / visits + EXPLORATION * ((self.availability as f64).ln() / visits).sqrt()
+    }
+}
+
+// Single-observer information set Monte Carlo tree search. Every iteration samples a
+// determinization of the hidden information and descends the tree using only the moves that are
+// legal in that sample, so the statistics are shared across all samples. Moves are told apart by
+// their `Determinize::key`, so a move stands for the same thing in every sample. Like `alphabeta`
+// the score is taken to be from the perspective of the player to move at the root.
+//
+// Every move played in an iteration counts as a node. Iterations run until one of the limits of
+// `config` is reached, or `ISMCTS_ITERATIONS` of them without a time or node limit. The score is how often the best move
+// won, from 0 to 1, and the depth the deepest the tree grew. The principal variation is only the
+// best move since the moves after it depend on the cards dealt.
+pub fn ismcts<S: Determinize, E: Evaluator<S>, R: Rng>(state: &S, evaluator: &E, config: &SearchConfig, rng: &mut R) -> SearchResult<S::Move, f64> {
+    let start = Instant::now();
+    let mut limits = Limits::new(config, start);
+    let unlimited = config.time_limit.is_none() && config.node_limit.is_none();
+    let mut tree: Vec<Node<S::Key>> = vec![Node::new(None, None)];
+    let possible_moves = state.generate_moves();
+    let mut iterations = 0;
+    let mut nodes = 0;
+    let mut deepest = 0;
+
+    // The first iteration always runs, so there is a move to play
+    let searching = !state.is_terminal() && !possible_moves.is_empty();
+    while searching && (iterations == 0 || !(limits.reached || (unlimited && iterations >= ISMCTS_ITERATIONS))) {
+        iterations += 1;
+        let mut determinization = state.determinize(rng);
+        let mut current = 0;
+        let mut depth: usize = 0;
+
+        // Selection and expansion
+        while !determinization.is_terminal() {
+            let possible_moves: Vec<(S::Move, S::Key)> = determinization.generate_moves().into_iter()
+                .map(|mov| (mov, determinization.key(&mov)))
+                .collect();
+            let mut untried = Vec::new();
+            for &(mov, ref key) in possible_moves.iter() {
+                match tree[current].children.iter().find(|&&child| tree[child].key.as_ref() == Some(key)) {
+                    Some(&child) => tree[child].availability += 1,
+                    None => untried.push(mov),
+                }
+            }
+
+            if let Some(&mov) = rng.choose(&untried) {
+                let child = tree.len();
+                tree.push(Node::new(Some(determinization.key(&mov)), Some(current)));
+                tree[current].children.push(child);
+                determinization.apply(&mov);
+                determinization.deal();
+                nodes += 1;
+                limits.check(nodes);
+                current = child;
+                depth += 1;
+                break;
+            }
+
+            let selected = tree[current].children.iter()
+                .filter_map(|&child| {
+                    possible_moves.iter().find(|(_, key)| tree[child].key.as_ref() == Some(key)).map(|&(mov, _)| (child, mov))
+                })
+                .max_by(|&(a, _), &(b, _)| tree[a].ucb().partial_cmp(&tree[b].ucb()).unwrap_or(Ordering::Equal));
+            match selected {
+                Some((child, mov)) => {
+                    determinization.apply(&mov);
+                    determinization.deal();
+                    nodes += 1;
+                    limits.check(nodes);
+                    current = child;
+                    depth += 1;
+                },
+                None => break,
+            }
+        }
+        deepest = cmp::max(deepest, depth);
+
+        // Playout
+        for _ in 0..PLAYOUT_DEPTH {
+            if determinization.is_terminal() {
+                break;
+            }
+            let possible_moves = determinization.generate_moves();
+            match rng.choose(&possible_moves) {
+                Some(&mov) => {
+                    determinization.apply(&mov);
+                    determinization.deal();
+                    nodes += 1;
+                    limits.check(nodes);
+                },
+                None => break,
+            }
+        }
+
+        let score = evaluator.evaluate(&determinization);
+        let root_reward = match score.cmp(&score.neg()) {
+            Ordering::Greater => 1.0,
+            Ordering::Less => 0.0,
+            Ordering::Equal => 0.5,
+        };
+
+        // Backpropagation, the root player makes the moves at odd depths
+        let mut node = Some(current);
+        while let Some(index) = node {
+            tree[index].visits += 1;
+            tree[index].reward += if depth % 2 == 1 { root_reward } else { 1.0 - root_reward };
+            node = tree[index].parent;
+            depth = depth.saturating_sub(1);
+        }
+    }
+
+    let most_visits = tree[0].children.iter().map(|&child| tree[child].visits).max().unwrap_or(0);
+    let best: Vec<usize> = tree[0].children.iter().cloned().filter(|&child| tree[child].visits == most_visits).collect();
+    let moves: Vec<S::Move> = possible_moves.into_iter()
+        .filter(|mov| {
+            let key = state.key(mov);
+            best.iter().any(|&child| tree[child].key.as_ref() == Some(&key))
+        })
+        .collect();
+    let score = best.first().map_or(0.0, |&child| tree[child].reward / tree[child].visits as f64);
+
+    SearchResult {
+        pv: moves.first().cloned().into_iter().collect(),
+        moves,
+        score,
+        nodes,
+        depth: deepest,
+        cutoffs: 0,
+        elapsed: start.elapsed(),
+    }
+}
@@ -1,68 +1,129 @@
-use std::cmp;
+use std::time::Instant;
 use algo::state::{State, Score};
+use algo::evaluator::Evaluator;
+use algo::result::{SearchResult, Stats};
+use algo::config::{SearchConfig, Limits};
 
-pub fn minimax<S: State>(mut state: &mut S) -> Vec<S::Move> {
-    let possible_moves = state.generate_moves();
+// When a limit is reached the root move that was being searched is dropped, unless it was the
+// first one
+pub fn minimax<S: State, E: Evaluator<S>>(state: &mut S, evaluator: &E, config: &SearchConfig) -> SearchResult<S::Move, S::Score> {
+    let start = Instant::now();
+    let max_depth = config.depth.saturating_sub(1);
+    let mut stats = Stats::default();
+    let mut limits = Limits::new(config, start);
+    let mut possible_moves = state.generate_moves();
+    if config.prune {
+        state.prune(&mut possible_moves);
+    }
     let mut best_moves: Vec<S::Move> = Vec::with_capacity(5);
     let mut best_score = S::Score::min_value();
+    let mut pv = Vec::new();
 
     for mov in possible_moves.into_iter() {
+        let mut line = Vec::new();
         state.apply(&mov);
-        let score = min::<S>(&mut state, 5);
+        let score = min(state, &mut stats, &mut limits, evaluator, config.prune, max_depth, &mut line);
+        state.undo(&mov);
 
+        if limits.reached && !best_moves.is_empty() {
+            break;
+        }
         if score == best_score {
             best_moves.push(mov);
         } else if score > best_score {
             best_score = score;
             best_moves.clear();
             best_moves.push(mov);
+            pv.clear();
+            pv.push(mov);
+            pv.append(&mut line);
+        }
+        if limits.reached {
+            break;
         }
-        state.undo(&mov);
     }
 
-    best_moves
+    SearchResult {
+        moves: best_moves,
+        score: best_score,
+        pv,
+        nodes: stats.nodes,
+        depth: max_depth + 1,
+        cutoffs: stats.cutoffs,
+        elapsed: start.elapsed(),
+    }
 }
 
-fn min<S: State>(mut state: &mut S, depth: usize) -> S::Score {
-    if depth == 0 || state.is_terminal() {
-        return state.score();
+fn min<S: State, E: Evaluator<S>>(state: &mut S, stats: &mut Stats, limits: &mut Limits, evaluator: &E, prune: bool, depth: usize, pv: &mut Vec<S::Move>) -> S::Score {
+    stats.nodes += 1;
+    if limits.check(stats.nodes) || depth == 0 || state.is_terminal() {
+        return evaluator.evaluate(state);
     }
 
-    let possible_moves = state.generate_moves();
+    let mut possible_moves = state.generate_moves();
+    if prune {
+        state.prune(&mut possible_moves);
+    }
     if possible_moves.is_empty() {
-        return state.score();
+        return evaluator.evaluate(state);
     }
     let mut worst_score = S::Score::max_value();
+    let mut best_line = Vec::new();
 
     for mov in possible_moves.iter() {
+        let mut line = Vec::new();
         state.apply(mov);
-        let score = max::<S>(&mut state, depth - 1);
+        let score = max(state, stats, limits, evaluator, prune, depth - 1, &mut line);
         state.undo(mov);
 
-        worst_score = cmp::min(worst_score, score);
+        if score < worst_score {
+            worst_score = score;
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        if limits.reached {
+            break;
+        }
     }
     
+    pv.append(&mut best_line);
     worst_score
 }
 
-fn max<S: State>(mut state: &mut S, depth: usize) -> S::Score {
-    if depth == 0 || state.is_terminal() {
-        return state.score();
+fn max<S: State, E: Evaluator<S>>(state: &mut S, stats: &mut Stats, limits: &mut Limits, evaluator: &E, prune: bool, depth: usize, pv: &mut Vec<S::Move>) -> S::Score {
+    stats.nodes += 1;
+    if limits.check(stats.nodes) || depth == 0 || state.is_terminal() {
+        return evaluator.evaluate(state);
     }
 
-    let possible_moves = state.generate_moves();
+    let mut possible_moves = state.generate_moves();
+    if prune {
+        state.prune(&mut possible_moves);
+    }
     if possible_moves.is_empty() {
-        return state.score();
+        return evaluator.evaluate(state);
     }
     let mut best_score = S::Score::min_value();
+    let mut best_line = Vec::new();
 
     for mov in possible_moves.iter() {
+        let mut line = Vec::new();
         state.apply(mov);
-        let score = min::<S>(&mut state, depth - 1);
+        let score = min(state, stats, limits, evaluator, prune, depth - 1, &mut line);
         state.undo(mov);
 
-        best_score = cmp::max(best_score, score);
+        if score > best_score {
+            best_score = score;
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        if limits.reached {
+            break;
+        }
     }
 
+    pv.append(&mut best_line);
     best_score
 }
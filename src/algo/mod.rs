@@ -1,8 +1,23 @@
 mod minimax;
 mod alphabeta;
+mod ismcts;
+mod multiplayer;
+mod endgame;
+pub mod config;
+pub mod evaluator;
+pub mod result;
 pub mod state;
 
 pub use self::minimax::minimax;
-pub use self::alphabeta::alphabeta;
+pub use self::alphabeta::{alphabeta, alphabeta_reporting, parallel_alphabeta};
+pub use self::ismcts::ismcts;
+pub use self::endgame::endgame;
+pub use self::multiplayer::{multiplayer, maxn, paranoid, MultiplayerSearch};
+pub use self::result::SearchResult;
+pub use self::config::{search, search_reporting, SearchConfig, Algorithm, TieBreak, Stop};
+pub use self::evaluator::{Evaluator, ScoreEvaluator, Negated};
 pub use self::state::State;
 pub use self::state::Score;
+pub use self::state::Determinize;
+pub use self::state::MultiplayerState;
+pub use self::state::{Endgame, Flipped, Outcome};
@@ -0,0 +1,317 @@
+use std::cmp;
+use std::time::Instant;
+use algo::state::{MultiplayerState, Score};
+use algo::result::{SearchResult, Stats};
+use algo::config::{SearchConfig, Limits};
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum MultiplayerSearch {
+    // Every player maximizes their own score
+    MaxN,
+    // Everyone else is assumed to be against the player to move at the root
+    Paranoid,
+}
+
+struct Search {
+    stats: Stats,
+    limits: Limits,
+}
+
+// Searches `config.depth` plies past the root move, or less when one of the limits of `config` is
+// reached first
+pub fn multiplayer<S: MultiplayerState>(state: &mut S, search: MultiplayerSearch, config: &SearchConfig) -> SearchResult<S::Move, S::Score> {
+    match search {
+        MultiplayerSearch::MaxN => maxn(state, config),
+        MultiplayerSearch::Paranoid => paranoid(state, config),
+    }
+}
+
+pub fn maxn<S: MultiplayerState>(state: &mut S, config: &SearchConfig) -> SearchResult<S::Move, S::Score> {
+    deepen(state, config, maxn_root)
+}
+
+pub fn paranoid<S: MultiplayerState>(state: &mut S, config: &SearchConfig) -> SearchResult<S::Move, S::Score> {
+    deepen(state, config, paranoid_root)
+}
+
+// Searches one ply deeper at a time with `root`, which returns the best score, the moves that reach
+// it and the principal variation. The last iteration that finished before a limit was reached is
+// the result, the first one always finishes.
+fn deepen<S, F>(state: &mut S, config: &SearchConfig, root: F) -> SearchResult<S::Move, S::Score>
+    where S: MultiplayerState, F: Fn(&mut S, &mut Search, usize) -> (S::Score, Vec<S::Move>, Vec<S::Move>) {
+    let start = Instant::now();
+    let mut search = Search {
+        stats: Stats::default(),
+        limits: Limits::new(config, start),
+    };
+    let mut best = (S::Score::min_value(), Vec::new(), Vec::new());
+    let mut searched = 0;
+
+    for depth in 0..config.depth + 1 {
+        let iteration = root(state, &mut search, depth);
+        if search.limits.reached && depth > 0 {
+            break;
+        }
+        best = iteration;
+        searched = depth + 1;
+        if search.limits.reached {
+            break;
+        }
+    }
+
+    let (score, moves, pv) = best;
+    SearchResult {
+        moves,
+        score,
+        pv,
+        nodes: search.stats.nodes,
+        depth: searched,
+        cutoffs: search.stats.cutoffs,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn maxn_root<S: MultiplayerState>(state: &mut S, search: &mut Search, depth: usize) -> (S::Score, Vec<S::Move>, Vec<S::Move>) {
+    let searcher = state.current_player();
+    let mut best_moves: Vec<S::Move> = Vec::with_capacity(5);
+    let mut best_score = S::Score::min_value();
+    let mut pv = Vec::new();
+
+    for mov in state.generate_moves().into_iter() {
+        let mut line = Vec::new();
+        state.apply(&mov);
+        let scores = maxn_node(state, search, depth, &mut line);
+        state.undo(&mov);
+
+        let score = scores[searcher];
+        if score == best_score {
+            best_moves.push(mov);
+        } else if score > best_score {
+            best_score = score;
+            best_moves.clear();
+            best_moves.push(mov);
+            pv.clear();
+            pv.push(mov);
+            pv.append(&mut line);
+        }
+        if search.limits.reached && depth > 0 {
+            break;
+        }
+    }
+
+    (best_score, best_moves, pv)
+}
+
+// Returns the score of every player at the end of the line the players would choose
+fn maxn_node<S: MultiplayerState>(state: &mut S, search: &mut Search, depth: usize, pv: &mut Vec<S::Move>) -> Vec<S::Score> {
+    search.stats.nodes += 1;
+    let scores = |state: &S| (0..state.players()).map(|player| state.score_for(player)).collect();
+    if search.limits.check(search.stats.nodes) || depth == 0 || state.is_terminal() {
+        return scores(state);
+    }
+
+    let possible_moves = state.generate_moves();
+    if possible_moves.is_empty() {
+        return scores(state);
+    }
+    let player = state.current_player();
+    let mut best_scores: Option<Vec<S::Score>> = None;
+    let mut best_line = Vec::new();
+
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        let scores = maxn_node(state, search, depth - 1, &mut line);
+        state.undo(mov);
+
+        if best_scores.as_ref().is_none_or(|best| scores[player] > best[player]) {
+            best_scores = Some(scores);
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        if search.limits.reached {
+            break;
+        }
+    }
+
+    pv.append(&mut best_line);
+    best_scores.unwrap()
+}
+
+// After the first move the others are searched with an alpha just below the best score, so a move
+// that can't reach it fails low below it and only moves that really score the same count as ties
+fn paranoid_root<S: MultiplayerState>(state: &mut S, search: &mut Search, depth: usize) -> (S::Score, Vec<S::Move>, Vec<S::Move>) {
+    let searcher = state.current_player();
+    let mut best_moves: Vec<S::Move> = Vec::with_capacity(5);
+    let mut best_score = S::Score::min_value();
+    let mut pv = Vec::new();
+
+    for mov in state.generate_moves().into_iter() {
+        let mut line = Vec::new();
+        let alpha = if best_moves.is_empty() { S::Score::min_value() } else { best_score.offset(-1) };
+        state.apply(&mov);
+        let score = paranoid_node(state, search, searcher, depth, alpha, S::Score::max_value(), &mut line);
+        state.undo(&mov);
+
+        if score == best_score {
+            best_moves.push(mov);
+        } else if score > best_score {
+            best_score = score;
+            best_moves.clear();
+            best_moves.push(mov);
+            pv.clear();
+            pv.push(mov);
+            pv.append(&mut line);
+        }
+        if search.limits.reached && depth > 0 {
+            break;
+        }
+    }
+
+    (best_score, best_moves, pv)
+}
+
+// Alphabeta where the searcher maximizes and every other player minimizes the searcher's score
+fn paranoid_node<S: MultiplayerState>(state: &mut S, search: &mut Search, searcher: usize, depth: usize, mut alpha: S::Score, mut beta: S::Score, pv: &mut Vec<S::Move>) -> S::Score {
+    search.stats.nodes += 1;
+    if search.limits.check(search.stats.nodes) || depth == 0 || state.is_terminal() {
+        return state.score_for(searcher);
+    }
+
+    let possible_moves = state.generate_moves();
+    if possible_moves.is_empty() {
+        return state.score_for(searcher);
+    }
+    let maximizing = state.current_player() == searcher;
+    let mut best_score = if maximizing { S::Score::min_value() } else { S::Score::max_value() };
+    let mut best_line = Vec::new();
+
+    for mov in possible_moves.iter() {
+        let mut line = Vec::new();
+        state.apply(mov);
+        let score = paranoid_node(state, search, searcher, depth - 1, alpha, beta, &mut line);
+        state.undo(mov);
+
+        if (maximizing && score > best_score) || (!maximizing && score < best_score) {
+            best_score = score;
+            best_line.clear();
+            best_line.push(*mov);
+            best_line.append(&mut line);
+        }
+        if maximizing {
+            alpha = cmp::max(alpha, score);
+        } else {
+            beta = cmp::min(beta, score);
+        }
+
+        if beta <= alpha {
+            search.stats.cutoffs += 1;
+            break;
+        }
+        if search.limits.reached {
+            break;
+        }
+    }
+
+    pv.append(&mut best_line);
+    best_score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::state::State;
+
+    // Player 0 picks a branch, player 1 a twig and player 2 a leaf. Positions before a leaf score
+    // nothing.
+    struct Tree {
+        path: Vec<u8>,
+    }
+
+    const LEAVES: [[[[i64; 3]; 2]; 2]; 2] = [
+        [[[2, 0, 0], [3, 0, 1]], [[4, 0, 1], [5, 0, 0]]],
+        [[[2, 3, 0], [6, 3, 1]], [[0, 2, 1], [7, 2, 0]]],
+    ];
+
+    impl State for Tree {
+        type Score = i64;
+        type Move = u8;
+        type Stages = ();
+
+        fn score(&self) -> i64 {
+            self.score_for(0)
+        }
+
+        fn generate_moves(&self) -> Vec<u8> {
+            vec![0, 1]
+        }
+
+        fn is_terminal(&self) -> bool {
+            self.path.len() == 3
+        }
+
+        fn apply(&mut self, mov: &u8) {
+            self.path.push(*mov);
+        }
+
+        fn undo(&mut self, _: &u8) {
+            self.path.pop();
+        }
+    }
+
+    impl MultiplayerState for Tree {
+        fn players(&self) -> usize {
+            3
+        }
+
+        fn current_player(&self) -> usize {
+            self.path.len() % 3
+        }
+
+        fn score_for(&self, player: usize) -> i64 {
+            if self.path.len() < 3 {
+                return 0;
+            }
+            LEAVES[self.path[0] as usize][self.path[1] as usize][self.path[2] as usize][player]
+        }
+    }
+
+    #[test]
+    fn maxn_expects_everyone_to_play_for_themselves() {
+        let result = multiplayer(&mut Tree { path: Vec::new() }, MultiplayerSearch::MaxN, &SearchConfig::default());
+        assert_eq!(result.moves, vec![1]);
+        assert_eq!(result.score, 6);
+        assert_eq!(result.pv, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn paranoid_expects_everyone_to_play_against_it() {
+        // Branch 1 cuts off at 2 after its first leaf, but player 2 can hold it to 0, so it isn't a
+        // tie with branch 0
+        let result = multiplayer(&mut Tree { path: Vec::new() }, MultiplayerSearch::Paranoid, &SearchConfig::default());
+        assert_eq!(result.moves, vec![0]);
+        assert_eq!(result.score, 2);
+        assert_eq!(result.pv, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn stop_at_the_depth_and_limits() {
+        let config = SearchConfig {
+            depth: 1,
+            ..SearchConfig::default()
+        };
+        let result = multiplayer(&mut Tree { path: Vec::new() }, MultiplayerSearch::Paranoid, &config);
+        assert_eq!((result.depth, result.score, result.moves), (2, 0, vec![0, 1]));
+
+        let config = SearchConfig::default();
+        config.stop.stop();
+        for search in [MultiplayerSearch::MaxN, MultiplayerSearch::Paranoid].iter() {
+            let result = multiplayer(&mut Tree { path: Vec::new() }, *search, &config);
+            assert_eq!((result.depth, result.moves), (1, vec![0, 1]));
+        }
+    }
+}
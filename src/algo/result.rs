@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SearchResult<M, S> {
+    // All root moves that share the best score, the one `pv` starts with first and the others in
+    // the order they were generated
+    pub moves: Vec<M>,
+    pub score: S,
+    // The expected line of play, starting with the first of `moves`
+    pub pv: Vec<M>,
+    pub nodes: u64,
+    // Plies searched, counting the root move
+    pub depth: usize,
+    pub cutoffs: u64,
+    pub elapsed: Duration,
+}
+
+#[derive(Default)]
+pub(crate) struct Stats {
+    pub nodes: u64,
+    pub cutoffs: u64,
+}
@@ -1,9 +1,14 @@
+use rand::Rng;
 use std::fmt;
+use std::hash::Hash;
 
 pub trait Score: Copy + Ord + fmt::Display {
     fn min_value() -> Self;
     fn max_value() -> Self;
     fn neg(&self) -> Self;
+    // The score `delta` steps away from this one, saturating at the minimum and maximum. Used to
+    // build null and aspiration windows.
+    fn offset(&self, delta: i64) -> Self;
 }
 
 impl Score for i64 {
@@ -18,15 +23,162 @@ impl Score for i64 {
     fn neg(&self) -> Self {
         -self
     }
+
+    fn offset(&self, delta: i64) -> Self {
+        self.saturating_add(delta)
+    }
 }
 
 pub trait State {
     type Score: Score;
-    type Move: Copy + fmt::Debug;
+    type Move: Copy + Eq + Hash + fmt::Debug;
+    // What generating the stages of a position found so far, so a stage can reuse the work done
+    // for another one. Every position starts with the default.
+    type Stages: Default;
 
     fn score(&self) -> Self::Score;
     fn generate_moves(&self) -> Vec<Self::Move>;
+    // The moves can be generated in stages, most promising first, so a search that cuts off early
+    // doesn't pay for generating the rest. Returns `None` after the last stage. Together the stages
+    // hold the same moves as `generate_moves`, by default all in the first one. Every stage is
+    // asked for at most once with the same `stages`, in any order.
+    fn generate_stage(&self, stage: usize, _stages: &mut Self::Stages) -> Option<Vec<Self::Move>> {
+        if stage == 0 {
+            Some(self.generate_moves())
+        } else {
+            None
+        }
+    }
+    // The stage `mov` would be generated in if it's legal, so a move that worked elsewhere can be
+    // tried before the rest of the moves are generated
+    fn stage_of(&self, _mov: &Self::Move) -> usize {
+        0
+    }
+    // Leaves out moves that are never better than one of the others, for searches with
+    // `SearchConfig::prune`. Called on every stage apart, so a move must be pruned in favour of one
+    // from the same stage. By default every move is kept.
+    fn prune(&self, _moves: &mut Vec<Self::Move>) {}
     fn is_terminal(&self) -> bool;
     fn apply(&mut self, &Self::Move);
     fn undo(&mut self, &Self::Move);
 }
+
+// A state with hidden information, like the order of the decks. Searching a determinization
+// pretends the hidden information is known, so `determinize` must only resample what the side to
+// move can't see.
+pub trait Determinize: State + Clone {
+    // What a move does, without what differs between determinizations, like where in the market
+    // the card it takes lies
+    type Key: Clone + PartialEq;
+
+    fn determinize<R: Rng>(&self, rng: &mut R) -> Self;
+    // Moves of different determinizations with the same key are the same move
+    fn key(&self, mov: &Self::Move) -> Self::Key;
+
+    // Reveal whatever a move makes visible, like dealing a new card after one is taken. Only
+    // called when playing forward, never between `apply` and `undo`.
+    fn deal(&mut self);
+}
+
+// A state for games with more than two players, who take turns in order
+pub trait MultiplayerState: State {
+    fn players(&self) -> usize;
+    // The index of the player to move, from 0 to `players() - 1`
+    fn current_player(&self) -> usize;
+    // The value of the position for `player`, higher is better for them
+    fn score_for(&self, player: usize) -> Self::Score;
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    // The outcome for the other player
+    pub fn opposite(&self) -> Outcome {
+        match *self {
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Win => Outcome::Loss,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Loss => write!(f, "loss"),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Win => write!(f, "win"),
+        }
+    }
+}
+
+// A state whose result can be solved exactly once the game is close to over
+pub trait Endgame: State {
+    // The result of a finished game, from the same perspective as `score`
+    fn outcome(&self) -> Option<Outcome>;
+    // Whether the end is near enough that solving is worth trying
+    fn is_endgame(&self) -> bool;
+}
+
+// The same game with outcomes and scores from the other player's side, for solving on behalf of the
+// minimizing side
+pub struct Flipped<'a, S: 'a>(pub &'a mut S);
+
+impl<'a, S: State> State for Flipped<'a, S> {
+    type Score = S::Score;
+    type Move = S::Move;
+    type Stages = S::Stages;
+
+    fn score(&self) -> S::Score {
+        self.0.score().neg()
+    }
+
+    fn generate_moves(&self) -> Vec<S::Move> {
+        self.0.generate_moves()
+    }
+
+    fn generate_stage(&self, stage: usize, stages: &mut S::Stages) -> Option<Vec<S::Move>> {
+        self.0.generate_stage(stage, stages)
+    }
+
+    fn stage_of(&self, mov: &S::Move) -> usize {
+        self.0.stage_of(mov)
+    }
+
+    fn prune(&self, moves: &mut Vec<S::Move>) {
+        self.0.prune(moves)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.0.is_terminal()
+    }
+
+    fn apply(&mut self, mov: &S::Move) {
+        self.0.apply(mov)
+    }
+
+    fn undo(&mut self, mov: &S::Move) {
+        self.0.undo(mov)
+    }
+}
+
+impl<'a, S: Endgame> Endgame for Flipped<'a, S> {
+    fn outcome(&self) -> Option<Outcome> {
+        self.0.outcome().map(|outcome| outcome.opposite())
+    }
+
+    fn is_endgame(&self) -> bool {
+        self.0.is_endgame()
+    }
+}
@@ -0,0 +1,85 @@
+extern crate splendimax;
+
+use std::env;
+use std::io;
+use std::io::{BufRead, Write};
+use std::process;
+use std::sync::mpsc::channel;
+use std::thread;
+use splendimax::algo::SearchConfig;
+use splendimax::engine::Engine;
+use splendimax::eval::Weights;
+
+struct Options {
+    weights: Weights,
+    search: SearchConfig,
+}
+
+fn usage() -> ! {
+    println!("usage: splendimax-engine [--weights FILE] {}", SearchConfig::USAGE);
+    println!();
+    println!("Reads commands of a UCI-like protocol from stdin, like position seed 42 moves take KBG,");
+    println!("go movetime 2000 and stop, and writes the replies to stdout. The options are the defaults");
+    println!("that setoption changes.");
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        weights: Weights::default(),
+        search: SearchConfig::default(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--weights" => options.weights = Weights::load(&value).unwrap_or_else(|err| {
+                println!("couldn't load {}: {}", value, err);
+                process::exit(1);
+            }),
+            _ => match options.search.set(&arg, &value) {
+                Ok(true) => (),
+                Ok(false) => usage(),
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+        }
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let (sender, replies) = channel::<String>();
+    // The search thread replies too, so everything is written from here
+    let printer = thread::spawn(move || {
+        let stdout = io::stdout();
+        for reply in replies {
+            let mut stdout = stdout.lock();
+            let _ = writeln!(stdout, "{}", reply);
+            let _ = stdout.flush();
+        }
+    });
+
+    {
+        let mut engine = Engine::new(sender);
+        engine.weights = options.weights;
+        engine.config = options.search;
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => if !engine.handle(&line) {
+                    break;
+                },
+                Err(_) => break,
+            }
+        }
+    }
+    let _ = printer.join();
+}
@@ -0,0 +1,150 @@
+extern crate splendimax;
+
+use std::env;
+use std::io;
+use std::io::Write;
+use std::process;
+use splendimax::algo::state::State as AlgoState;
+use splendimax::algo::{search, Evaluator, Negated, SearchConfig};
+use splendimax::eval::{Weights, WeightedEvaluator};
+use splendimax::record::GameRecord;
+use splendimax::state::{Move, State};
+
+struct Options {
+    record: String,
+    weights: Weights,
+    search: SearchConfig,
+}
+
+fn usage() -> ! {
+    println!("usage: splendimax-replay FILE [--weights FILE] {}", SearchConfig::USAGE);
+    println!();
+    println!("Steps through the game record in FILE. The search options are used by the analyse command.");
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        record: match args.next() {
+            Some(ref record) if !record.starts_with("--") => record.clone(),
+            _ => usage(),
+        },
+        weights: Weights::default(),
+        search: SearchConfig::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--weights" => options.weights = Weights::load(&value).unwrap_or_else(|err| {
+                println!("couldn't load {}: {}", value, err);
+                process::exit(1);
+            }),
+            _ => match options.search.set(&arg, &value) {
+                Ok(true) => (),
+                Ok(false) => usage(),
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+        }
+    }
+    options
+}
+
+fn side(state: &State) -> &'static str {
+    if state.players_turn { "player" } else { "adversary" }
+}
+
+// `states[i]` is the position after `moves[..i]`
+fn show(states: &[State], moves: &[Move], ply: usize, evaluator: &WeightedEvaluator) {
+    let state = &states[ply];
+    let mut stdout = io::stdout();
+    let _ = state.print(&mut stdout);
+    println!();
+    if ply > 0 {
+        println!("move {}/{}: {} {}", ply, moves.len(), side(&states[ply - 1]), moves[ply - 1]);
+    } else {
+        println!("start, {} moves", moves.len());
+    }
+    match moves.get(ply) {
+        Some(mov) => println!("next: {} {}", side(state), mov),
+        None => println!("end of the game"),
+    }
+    // Scores are from the player's side, like the search's
+    println!("evaluation: {}", evaluator.evaluate(state));
+}
+
+// Searches for the best moves of the side to move, the score is from their side
+fn analyse<E: Evaluator<State> + Sync>(state: &State, evaluator: &E, config: &SearchConfig) {
+    let result = search(&mut state.clone(), evaluator, config);
+    let moves: Vec<String> = result.moves.iter().map(|mov| mov.to_string()).collect();
+    let pv: Vec<String> = result.pv.iter().map(|mov| mov.to_string()).collect();
+    println!("score {} for the {}, depth {} nodes {} time {:?}", result.score, side(state), result.depth, result.nodes, result.elapsed);
+    println!("best {}", moves.join(", "));
+    println!("pv {}", pv.join(", "));
+}
+
+fn main() {
+    let options = parse_options();
+    let record = GameRecord::load(&options.record).unwrap_or_else(|err| {
+        println!("couldn't load {}: {}", options.record, err);
+        process::exit(1);
+    });
+    let evaluator = WeightedEvaluator::new(options.weights);
+
+    let mut states = vec![record.setup.state()];
+    for mov in record.moves.iter() {
+        let mut state = states[states.len() - 1].clone();
+        state.apply(mov);
+        state.refill_market();
+        states.push(state);
+    }
+
+    println!("player: {}", record.player);
+    println!("adversary: {}", record.adversary);
+    let mut ply = 0;
+    show(&states, &record.moves, ply, &evaluator);
+    let stdin = io::stdin();
+    loop {
+        print!("(n)ext, (p)revious, (g)o N, (s)tart, (e)nd, (a)nalyse, (q)uit> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let mut words = line.split_whitespace();
+        match words.next().unwrap_or("n") {
+            "n" => ply = (ply + 1).min(record.moves.len()),
+            "p" => ply = ply.saturating_sub(1),
+            "s" => ply = 0,
+            "e" => ply = record.moves.len(),
+            "g" => match words.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n <= record.moves.len() => ply = n,
+                _ => {
+                    println!("expected a move number from 0 to {}", record.moves.len());
+                    continue;
+                },
+            },
+            "a" => {
+                if states[ply].players_turn {
+                    analyse(&states[ply], &evaluator, &options.search);
+                } else {
+                    analyse(&states[ply], &Negated(evaluator.clone()), &options.search);
+                }
+                continue;
+            },
+            "q" => break,
+            command => {
+                println!("unknown command {:?}", command);
+                continue;
+            },
+        }
+        show(&states, &record.moves, ply, &evaluator);
+    }
+}
@@ -0,0 +1,65 @@
+extern crate splendimax;
+
+use std::env;
+use std::net::TcpListener;
+use std::process;
+use std::sync::Arc;
+use splendimax::algo::SearchConfig;
+use splendimax::server::{serve, Server};
+use splendimax::tournament::Bot;
+
+struct Options {
+    address: String,
+    bot: Bot,
+}
+
+fn usage() -> ! {
+    println!("usage: splendimax-server [--address HOST:PORT] [--bot OPTIONS]");
+    println!();
+    println!("Serves a JSON API to play and analyse games over HTTP, see the server module for the");
+    println!("endpoints. The address defaults to 127.0.0.1:8080. Requests that don't name a bot are");
+    println!("answered by --bot, which is described like the bots of splendimax-tournament, as in");
+    println!("--bot greedy or --bot \"--depth 4 --weights tuned.txt\" with --weights FILE and");
+    println!("{}", SearchConfig::USAGE);
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        address: "127.0.0.1:8080".to_string(),
+        bot: Bot::parse("").unwrap(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--address" => options.address = value,
+            "--bot" => match Bot::parse(&value) {
+                Ok(bot) => options.bot = bot,
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+            _ => usage(),
+        }
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let listener = TcpListener::bind(&options.address).unwrap_or_else(|err| {
+        println!("couldn't listen on {}: {}", options.address, err);
+        process::exit(1);
+    });
+    println!("listening on http://{}, bot {}", options.address, options.bot.name);
+    if let Err(err) = serve(Arc::new(Server::new(options.bot)), listener) {
+        println!("{}", err);
+        process::exit(1);
+    }
+}
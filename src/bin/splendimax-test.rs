@@ -1,88 +1,124 @@
 extern crate splendimax;
 extern crate rand;
 
+use std::env;
 use std::io;
-// use std::time::Duration;
-// use std::thread::sleep;
-use splendimax::algo::state::State as AlgoState;
-use splendimax::algo::state::Score;
-use splendimax::algo::alphabeta;
-use splendimax::state::State;
+use std::path::Path;
+use std::process;
+use splendimax::agent::{Game, SearchAgent};
+use splendimax::algo::SearchConfig;
+use splendimax::book::{Book, BOOK_PLIES};
+use splendimax::eval::Weights;
+use splendimax::record::{GameRecord, Setup};
+use splendimax::tournament::MAXIMUM_PLIES;
 use rand::{thread_rng, Rng};
 
-struct OppositeState<'a, S: AlgoState>(&'a mut S) where S: 'a;
-
-impl<'a, S: AlgoState> AlgoState for OppositeState<'a, S> {
-    type Score = S::Score;
-    type Move = S::Move;
-
-    fn score(&self) -> Self::Score {
-        self.0.score().neg()
-    }
+struct Options {
+    search: SearchConfig,
+    games: usize,
+    // Game N is dealt from seed N modulo this, so the same deals come up again
+    seeds: Option<u64>,
+    // Where the opening book is read from and written to after every game
+    book: Option<String>,
+    book_plies: usize,
+    // Every game is saved to its own file in this directory
+    records: Option<String>,
+}
 
-    fn generate_moves(&self) -> Vec<Self::Move> {
-        self.0.generate_moves()
-    }
+fn usage() -> ! {
+    println!("usage: splendimax-test [--games N] [--seeds N] [--book FILE] [--book-plies N] [--records DIR] {}", SearchConfig::USAGE);
+    println!();
+    println!("Plays --games games between two bots. With --book the first --book-plies moves of every");
+    println!("game are added to the opening book in FILE, with how the game went. Only positions that");
+    println!("come up again are played from the book, so give --seeds to deal every game from one of N");
+    println!("seeds instead of at random. With --records every game is saved to DIR/game-N.txt.");
+    process::exit(1);
+}
 
-    fn is_terminal(&self) -> bool {
-        self.0.is_terminal()
-    }
+fn parse_options() -> Options {
+    let mut options = Options {
+        search: SearchConfig::default(),
+        games: 1,
+        seeds: None,
+        book: None,
+        book_plies: BOOK_PLIES,
+        records: None,
+    };
 
-    fn apply(&mut self, mov: &Self::Move) {
-        self.0.apply(mov);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--games" => options.games = value.parse().unwrap_or_else(|_| usage()),
+            "--seeds" => options.seeds = Some(value.parse().ok().filter(|&seeds| seeds > 0).unwrap_or_else(|| usage())),
+            "--book" => options.book = Some(value),
+            "--book-plies" => options.book_plies = value.parse().unwrap_or_else(|_| usage()),
+            "--records" => options.records = Some(value),
+            _ => match options.search.set(&arg, &value) {
+                Ok(true) => (),
+                Ok(false) => usage(),
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+        }
     }
+    options
+}
 
-    fn undo(&mut self, mov: &Self::Move) {
-        self.0.undo(mov);
+fn play(setup: Setup, config: &SearchConfig) -> GameRecord {
+    let name = config.to_string();
+    let player = SearchAgent::new(&name, config.clone(), Weights::default());
+    let adversary = SearchAgent::new(&name, config.clone(), Weights::default());
+    let mut game = Game::new(setup, Box::new(player), Box::new(adversary));
+    if let Err(err) = game.play(MAXIMUM_PLIES) {
+        println!("{}", err);
+        process::exit(1);
     }
+    let _ = game.state.print(&mut io::stdout());
+    println!("round: {}", game.record.moves.len() / 2);
+    game.record
 }
 
 fn main() {
-    //loop {
-    let mut stdout = io::stdout();
-    let mut state = State::new(2);
-    let mut rng = thread_rng();
-    let mut round = 0;
-    loop {
-        if state.is_terminal() {
-            state.print(&mut stdout);
-            println!("round: {}", round);
-            break
-        }
-        let moves;
-        if state.players_turn {
-            moves = alphabeta(&mut state);
-        } else {
-            let mut opposite = OppositeState(&mut state);
-            moves = alphabeta(&mut opposite);
-            round += 1;
-        }
-
-        if let Some(mov) = rng.choose(&moves) {
-            // state.print(&mut stdout);
-            // println!("{:?}", mov);
-            state.apply(&mov);
-        } else {
-            state.print(&mut stdout);
-            panic!("No moves");
-        }
+    let options = parse_options();
+    let mut book = match options.book {
+        Some(ref path) => match Book::load(path) {
+            Ok(book) => Some(book),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Some(Book::new()),
+            Err(err) => {
+                println!("couldn't load {}: {}", path, err);
+                process::exit(1);
+            },
+        },
+        None => None,
+    };
 
-        if state.cards1.len() < 4 {
-            if let Some(card) = state.deck1.pop() {
-                state.cards1.push(card);
-            }
-        }
-        if state.cards2.len() < 4 {
-            if let Some(card) = state.deck2.pop() {
-                state.cards2.push(card);
+    for game in 0..options.games {
+        let seed = match options.seeds {
+            Some(seeds) => game as u64 % seeds,
+            None => thread_rng().gen(),
+        };
+        let record = play(Setup::Seed(seed), &options.search);
+        if let Some(ref dir) = options.records {
+            let path = Path::new(dir).join(format!("game-{}.txt", game + 1));
+            if let Err(err) = record.save(&path) {
+                println!("couldn't write {}: {}", path.display(), err);
+                process::exit(1);
             }
         }
-        if state.cards3.len() < 4 {
-            if let Some(card) = state.deck3.pop() {
-                state.cards3.push(card);
+
+        if let (Some(book), Some(path)) = (book.as_mut(), options.book.as_ref()) {
+            book.record_game(&record, options.book_plies);
+            if let Err(err) = book.save(path) {
+                println!("couldn't write {}: {}", path, err);
+                process::exit(1);
             }
+            println!("book: {} positions", book.len());
         }
-        // sleep(Duration::from_secs(1));
     }
-    //}
 }
@@ -0,0 +1,106 @@
+extern crate splendimax;
+extern crate rand;
+
+use std::env;
+use std::path::Path;
+use std::process;
+use splendimax::algo::{Endgame, Outcome, SearchConfig};
+use splendimax::tournament::{Bot, Tournament};
+use rand::{thread_rng, Rng, SeedableRng, StdRng};
+
+struct Options {
+    bots: Vec<Bot>,
+    games: usize,
+    // Deals are drawn from a generator seeded with this, so a tournament can be run again
+    seed: Option<u64>,
+    // Every game is saved to its own file in this directory
+    records: Option<String>,
+}
+
+fn usage() -> ! {
+    println!("usage: splendimax-tournament --bot OPTIONS --bot OPTIONS... [--games N] [--seed N] [--records DIR]");
+    println!();
+    println!("Plays --games games between every pair of bots, swapping seats after every game and");
+    println!("playing every deal from both seats, so an odd number of games is rounded up. A bot is");
+    println!("described by its options, like --bot \"--depth 4 --weights tuned.txt\", which can be");
+    println!("--weights FILE and");
+    println!("{}", SearchConfig::USAGE);
+    println!("The baselines --bot random and --bot greedy play random moves and the best move one");
+    println!("ply ahead.");
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        bots: Vec::new(),
+        games: 10,
+        seed: None,
+        records: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--bot" => match Bot::parse(&value) {
+                Ok(bot) => options.bots.push(bot),
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+            "--games" => options.games = value.parse().unwrap_or_else(|_| usage()),
+            "--seed" => options.seed = Some(value.parse().unwrap_or_else(|_| usage())),
+            "--records" => options.records = Some(value),
+            _ => usage(),
+        }
+    }
+    if options.bots.len() < 2 {
+        usage();
+    }
+    options
+}
+
+fn main() {
+    let options = parse_options();
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    println!("seed {}", seed);
+    let mut rng: StdRng = SeedableRng::from_seed(&[seed as usize][..]);
+
+    let mut tournament = Tournament::new(options.bots.clone());
+    let mut played = 0;
+    let result = tournament.run(options.games, || rng.gen(), |tournament, player, adversary, record| {
+        played += 1;
+        // From the side of the bot that moved first
+        let result = match record.replay() {
+            Ok(state) => format!("{} {}-{}", state.outcome().unwrap_or(Outcome::Draw), state.player.score(), state.adversary.score()),
+            Err(err) => err,
+        };
+        println!("game {}: {} vs {}: {} in {} plies", played, tournament.bots[player].name, tournament.bots[adversary].name, result, record.moves.len());
+
+        if let Some(ref dir) = options.records {
+            let path = Path::new(dir).join(format!("game-{}.txt", played));
+            if let Err(err) = record.save(&path) {
+                println!("couldn't write {}: {}", path.display(), err);
+                process::exit(1);
+            }
+        }
+    });
+    if let Err(err) = result {
+        println!("{}", err);
+        process::exit(1);
+    }
+
+    println!();
+    for (i, bot) in tournament.bots.iter().enumerate() {
+        println!("{}: {}", bot.name, tournament.total(i));
+        for (j, other) in tournament.bots.iter().enumerate() {
+            if i != j {
+                println!("    vs {}: {}", other.name, tournament.stats[i][j]);
+            }
+        }
+    }
+}
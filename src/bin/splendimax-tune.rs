@@ -0,0 +1,137 @@
+extern crate splendimax;
+extern crate rand;
+
+use std::env;
+use std::process;
+use splendimax::agent::{Game, SearchAgent};
+use splendimax::algo::{Endgame, Outcome, SearchConfig};
+use splendimax::eval::Weights;
+use splendimax::record::Setup;
+use splendimax::tournament::MAXIMUM_PLIES;
+use rand::{thread_rng, Rng};
+
+// The win weight only matters relative to the others at the end of the game, so it isn't tuned
+const TUNED: [&str; 7] = ["point", "card", "noble_color", "same_color", "reserved", "token", "joker"];
+
+struct Options {
+    iterations: usize,
+    games: usize,
+    weights: Weights,
+    out: String,
+    search: SearchConfig,
+}
+
+fn usage() -> ! {
+    println!("usage: splendimax-tune [--iterations N] [--games N] [--weights FILE] [--out FILE] {}", SearchConfig::USAGE);
+    println!();
+    println!("Tunes the evaluation weights with SPSA. Every iteration plays --games pairs of games");
+    println!("between two perturbed weight sets, with both seating orders on the same deal, and moves");
+    println!("the weights towards the winner. The result is written to --out after every iteration.");
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        iterations: 100,
+        games: 4,
+        weights: Weights::default(),
+        out: "weights.txt".to_string(),
+        search: SearchConfig::default(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+        match arg.as_str() {
+            "--iterations" => options.iterations = value.parse().unwrap_or_else(|_| usage()),
+            "--games" => options.games = value.parse().unwrap_or_else(|_| usage()),
+            "--weights" => options.weights = Weights::load(&value).unwrap_or_else(|err| {
+                println!("couldn't load {}: {}", value, err);
+                process::exit(1);
+            }),
+            "--out" => options.out = value,
+            _ => match options.search.set(&arg, &value) {
+                Ok(true) => (),
+                Ok(false) => usage(),
+                Err(err) => {
+                    println!("{}", err);
+                    usage();
+                },
+            },
+        }
+    }
+    options
+}
+
+// Returns 1 if `player` wins, -1 if `adversary` wins and 0 for a draw
+fn play(setup: &Setup, player: &Weights, adversary: &Weights, config: &SearchConfig) -> i64 {
+    let player = SearchAgent::new("player", config.clone(), *player);
+    let adversary = SearchAgent::new("adversary", config.clone(), *adversary);
+    let mut game = Game::new(setup.clone(), Box::new(player), Box::new(adversary));
+    if let Err(err) = game.play(MAXIMUM_PLIES) {
+        println!("{}", err);
+        process::exit(1);
+    }
+    match game.state.outcome() {
+        Some(Outcome::Win) => 1,
+        Some(Outcome::Loss) => -1,
+        _ => 0,
+    }
+}
+
+fn main() {
+    let options = parse_options();
+    let mut rng = thread_rng();
+
+    // SPSA works on real numbers, every weight is scaled by its starting magnitude so they all
+    // move at a comparable rate
+    let scales: Vec<f64> = TUNED.iter()
+        .map(|name| (options.weights.get(name).unwrap().abs() as f64).max(1.0))
+        .collect();
+    let mut theta: Vec<f64> = TUNED.iter()
+        .zip(scales.iter())
+        .map(|(name, scale)| options.weights.get(name).unwrap() as f64 / scale)
+        .collect();
+
+    let to_weights = |theta: &[f64]| {
+        let mut weights = options.weights;
+        for (i, name) in TUNED.iter().enumerate() {
+            *weights.get_mut(name).unwrap() = (theta[i] * scales[i]).round() as i64;
+        }
+        weights
+    };
+
+    for k in 0..options.iterations {
+        // Standard SPSA gain sequences
+        let a = 0.5 / (k as f64 + 10.0).powf(0.602);
+        let c = 0.2 / (k as f64 + 1.0).powf(0.101);
+
+        let delta: Vec<f64> = TUNED.iter().map(|_| if rng.gen() { 1.0 } else { -1.0 }).collect();
+        let plus: Vec<f64> = theta.iter().zip(delta.iter()).map(|(t, d)| t + c * d).collect();
+        let minus: Vec<f64> = theta.iter().zip(delta.iter()).map(|(t, d)| t - c * d).collect();
+        let plus_weights = to_weights(&plus);
+        let minus_weights = to_weights(&minus);
+
+        let mut result = 0;
+        for _ in 0..options.games {
+            let setup = Setup::Seed(rng.gen());
+            result += play(&setup, &plus_weights, &minus_weights, &options.search);
+            result -= play(&setup, &minus_weights, &plus_weights, &options.search);
+        }
+
+        let gradient = result as f64 / (2.0 * options.games as f64) / (2.0 * c);
+        for (t, d) in theta.iter_mut().zip(delta.iter()) {
+            *t += a * gradient * d;
+        }
+
+        let weights = to_weights(&theta);
+        println!("iteration {}: {:+} {:?}", k + 1, result, weights);
+        if let Err(err) = weights.save(&options.out) {
+            println!("couldn't write {}: {}", options.out, err);
+            process::exit(1);
+        }
+    }
+}
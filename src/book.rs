@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use algo::{Endgame, Outcome};
+use algo::State as AlgoState;
+use card::Card;
+use color::Color;
+use cost::Tokens;
+use record::GameRecord;
+use state::{Move, Player, State};
+
+// How many plies from the start of a game are recorded by default
+pub const BOOK_PLIES: usize = 8;
+
+// How many games a move must have been played in before it's played from the book, so a move that
+// happened to win once doesn't beat one that has been tested. Positions include the market, so
+// only games dealt alike get there, see `splendimax-test --seeds`.
+pub const BOOK_MIN_GAMES: u32 = 3;
+
+// 64 bit FNV-1a, which unlike the standard library's hasher is guaranteed to stay the same, so
+// book files keep working
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.0 ^= byte as u64;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+
+    fn tokens(&mut self, tokens: &Tokens) {
+        for color in Color::all() {
+            self.byte(tokens[color]);
+        }
+    }
+
+    fn cards(&mut self, cards: &[Card]) {
+        self.byte(cards.len() as u8);
+        for card in cards.iter() {
+            self.byte(card.color as u8);
+            self.byte(card.points);
+            self.tokens(&card.cost);
+        }
+    }
+
+    // The order of the cards a player owns doesn't matter, only what they pay for and their points
+    fn player(&mut self, player: &Player) {
+        self.tokens(&player.tokens);
+        self.tokens(&player.tokens_from_cards());
+        self.byte(player.score());
+        self.cards(&player.reserved);
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct BookMove {
+    // In the notation of `Move`'s `Display`
+    pub notation: String,
+    pub games: u32,
+    // Counted for the player who made the move
+    pub wins: u32,
+    pub draws: u32,
+}
+
+impl BookMove {
+    // Half a point for a draw
+    fn points(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games as f64
+    }
+}
+
+// Moves played from positions near the start of the game, with how the games went. The book is
+// written to a file with one `hash games wins draws move` line per move, like
+// `9c1f04a2b35e7d60 12 7 1 take KBG`. Lines starting with # are ignored.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct Book {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl Book {
+    pub fn new() -> Book {
+        Book::default()
+    }
+
+    // Hashes everything that can be seen: the market, the nobles, the bank and both players. The
+    // order of the decks is hidden, so positions that only differ in it have the same hash.
+    pub fn hash(state: &State) -> u64 {
+        let mut hasher = Fnv::new();
+        hasher.byte(state.players_turn as u8);
+        hasher.tokens(&state.bank);
+        hasher.cards(&state.cards1);
+        hasher.cards(&state.cards2);
+        hasher.cards(&state.cards3);
+        hasher.byte(state.nobles.len() as u8);
+        for noble in state.nobles.iter() {
+            hasher.tokens(&noble.cost);
+        }
+        hasher.player(&state.player);
+        hasher.player(&state.adversary);
+        hasher.0
+    }
+
+    // The number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Records that `mov` was played in `state`, in a game that ended with `outcome` for the player
+    // who made it
+    pub fn record(&mut self, state: &State, mov: &Move, outcome: Outcome) {
+        let notation = mov.to_string();
+        let moves = self.entries.entry(Book::hash(state)).or_default();
+        let i = match moves.iter().position(|book_move| book_move.notation == notation) {
+            Some(i) => i,
+            None => {
+                moves.push(BookMove {
+                    notation,
+                    games: 0,
+                    wins: 0,
+                    draws: 0,
+                });
+                moves.len() - 1
+            },
+        };
+        let book_move = &mut moves[i];
+        book_move.games += 1;
+        match outcome {
+            Outcome::Win => book_move.wins += 1,
+            Outcome::Draw => book_move.draws += 1,
+            Outcome::Loss => (),
+        }
+    }
+
+    // Records the first `plies` moves of `record`, each with how the game ended for the player who
+    // made it. Games that didn't end count as draws.
+    pub fn record_game(&mut self, record: &GameRecord, plies: usize) {
+        let outcome = record.replay().ok().and_then(|state| state.outcome()).unwrap_or(Outcome::Draw);
+        let mut state = record.setup.state();
+        for mov in record.moves.iter().take(plies) {
+            self.record(&state, mov, if state.players_turn { outcome } else { outcome.opposite() });
+            state.apply(mov);
+            state.refill_market();
+        }
+    }
+
+    // The move that scored best from `state` out of those played in at least `BOOK_MIN_GAMES`
+    // games, preferring the one played most often on ties. Moves that aren't legal, which only
+    // happens when two positions have the same hash, are left out.
+    pub fn lookup(&self, state: &State) -> Option<Move> {
+        let moves = self.entries.get(&Book::hash(state))?;
+        let best = moves.iter().filter(|book_move| book_move.games >= BOOK_MIN_GAMES).max_by(|a, b| {
+            a.points().total_cmp(&b.points()).then(a.games.cmp(&b.games)).then(b.notation.cmp(&a.notation))
+        })?;
+        state.parse_move(&best.notation).ok()
+    }
+
+    pub fn parse(input: &str) -> Result<Book, String> {
+        let mut book = Book::new();
+        for (number, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || format!("line {}: expected `hash games wins draws move`", number + 1);
+            let fields: Vec<&str> = line.splitn(5, ' ').collect();
+            if fields.len() != 5 {
+                return Err(error());
+            }
+            let hash = u64::from_str_radix(fields[0], 16).map_err(|_| error())?;
+            let numbers: Result<Vec<u32>, _> = fields[1..4].iter().map(|field| field.parse()).collect();
+            let numbers = numbers.map_err(|_| error())?;
+            if numbers[0] == 0 {
+                return Err(format!("line {}: a move must have been played in at least one game", number + 1));
+            }
+            if numbers[1] as u64 + numbers[2] as u64 > numbers[0] as u64 {
+                return Err(format!("line {}: more wins and draws than games", number + 1));
+            }
+            let mov: Move = fields[4].parse().map_err(|err| format!("line {}: {}", number + 1, err))?;
+            book.entries.entry(hash).or_default().push(BookMove {
+                notation: mov.to_string(),
+                games: numbers[0],
+                wins: numbers[1],
+                draws: numbers[2],
+            });
+        }
+        Ok(book)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        let mut input = String::new();
+        File::open(path)?.read_to_string(&mut input)?;
+        Book::parse(&input).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    // Sorted, so a book that grew only shows up as added lines in a diff
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort();
+        for hash in hashes.into_iter() {
+            let mut moves = self.entries[hash].clone();
+            moves.sort_by(|a, b| a.notation.cmp(&b.notation));
+            for book_move in moves.iter() {
+                writeln!(out, "{:016x} {} {} {} {}", hash, book_move.games, book_move.wins, book_move.draws, book_move.notation)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(&mut File::create(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent::{Game, GreedyAgent};
+    use record::Setup;
+    use tournament::MAXIMUM_PLIES;
+
+    #[test]
+    fn hash_ignores_hidden_cards() {
+        let state = State::new(2);
+        let mut shuffled = state.clone();
+        shuffled.deck1.reverse();
+        assert_eq!(Book::hash(&state), Book::hash(&shuffled));
+
+        let mut moved = state.clone();
+        let mov = moved.generate_moves()[0];
+        moved.apply(&mov);
+        assert!(Book::hash(&state) != Book::hash(&moved));
+    }
+
+    #[test]
+    fn record_and_lookup() {
+        let state = State::new(2);
+        let moves = state.generate_moves();
+        let mut book = Book::new();
+        assert_eq!(book.lookup(&state), None);
+
+        book.record(&state, &moves[0], Outcome::Loss);
+        book.record(&state, &moves[1], Outcome::Draw);
+        book.record(&state, &moves[2], Outcome::Win);
+        book.record(&state, &moves[2], Outcome::Loss);
+        book.record(&state, &moves[2], Outcome::Loss);
+        book.record(&state, &moves[3], Outcome::Win);
+        book.record(&state, &moves[3], Outcome::Draw);
+        book.record(&state, &moves[3], Outcome::Win);
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&state), Some(moves[3]));
+
+        let mut out = Vec::new();
+        book.write(&mut out).unwrap();
+        let parsed = Book::parse(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(parsed.lookup(&state), Some(moves[3]));
+        assert!(Book::parse("0123 1 2 3").is_err());
+        assert!(Book::parse("0123 1 2 0 pass").is_err());
+        assert!(Book::parse("0123 2 1 0 fly").is_err());
+        assert!(Book::parse("0123 2 1 0 take 2R drop W").is_ok());
+        assert!(Book::parse("0123 0 0 0 pass").is_err());
+        assert!(Book::parse("0123 1 4294967295 1 pass").is_err());
+    }
+
+    #[test]
+    fn lookup_needs_enough_games() {
+        let state = State::new(2);
+        let moves = state.generate_moves();
+        let mut book = Book::new();
+        book.record(&state, &moves[0], Outcome::Win);
+        assert_eq!(book.lookup(&state), None);
+
+        for outcome in [Outcome::Win, Outcome::Win, Outcome::Loss].iter() {
+            book.record(&state, &moves[1], *outcome);
+        }
+        assert_eq!(book.lookup(&state), Some(moves[1]));
+    }
+
+    #[test]
+    fn book_from_games_dealt_alike() {
+        let mut book = Book::new();
+        let mut first = Vec::new();
+        for _ in 0..BOOK_MIN_GAMES {
+            let mut game = Game::new(Setup::Seed(7), Box::new(GreedyAgent::new("greedy", 1)), Box::new(GreedyAgent::new("greedy", 2)));
+            game.play(MAXIMUM_PLIES).unwrap();
+            book.record_game(&game.record, BOOK_PLIES);
+            first.push(game.record.moves[0]);
+        }
+        // Alike bots play alike games
+        assert!(first.iter().all(|mov| *mov == first[0]));
+        assert_eq!(book.lookup(&Setup::Seed(7).state()), Some(first[0]));
+        assert_eq!(book.lookup(&Setup::Seed(8).state()), None);
+    }
+}
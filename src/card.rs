@@ -1,5 +1,9 @@
+use std::fmt;
+use std::str::FromStr;
 use cost::Tokens;
 use color::Color;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 fn create_card(color: Color, points: u8, black: u8, blue: u8, green: u8, red: u8, white: u8) -> Card {
     Card {
@@ -19,6 +23,7 @@ fn create_card(color: Color, points: u8, black: u8, blue: u8, green: u8, red: u8
 #[derive(Debug)]
 #[derive(Clone)]
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Card {
     pub color: Color,
     pub cost: Tokens,
@@ -132,3 +137,28 @@ impl Card {
     }
 }
 
+
+// The color the card pays for, its points and its cost, like `K1:4B`
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}:{}", self.color.code(), self.points, self.cost)
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Card, String> {
+        let error = || format!("expected a card like K1:4B, got {:?}", s);
+        let mut parts = s.splitn(2, ':');
+        let head = parts.next().unwrap_or("");
+        let cost = parts.next().ok_or_else(error)?;
+        let mut chars = head.chars();
+        let color = chars.next().and_then(Color::from_code).ok_or_else(error)?;
+        Ok(Card {
+            color,
+            cost: cost.parse()?,
+            points: chars.as_str().parse().map_err(|_| error())?,
+        })
+    }
+}
@@ -1,7 +1,12 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Copy)]
 #[derive(Clone)]
 #[derive(Debug)]
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Color {
     Black,
     Blue,
@@ -34,6 +39,19 @@ impl Color {
             Color::Joker => "J",
         }
     }
+
+    // The inverse of `code`, either case
+    pub fn from_code(code: char) -> Option<Color> {
+        match code.to_ascii_uppercase() {
+            'K' => Some(Color::Black),
+            'B' => Some(Color::Blue),
+            'G' => Some(Color::Green),
+            'R' => Some(Color::Red),
+            'W' => Some(Color::White),
+            'J' => Some(Color::Joker),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -111,4 +129,13 @@ mod tests {
         assert_eq!(iter.next(), Some(Color::White));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn from_code() {
+        for color in Color::all() {
+            assert_eq!(Color::from_code(color.code().chars().next().unwrap()), Some(color));
+        }
+        assert_eq!(Color::from_code('k'), Some(Color::Black));
+        assert_eq!(Color::from_code('X'), None);
+    }
 }
@@ -1,12 +1,19 @@
+use std::fmt;
 use std::ops::{Index, IndexMut, Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
 use color::Color;
 use std::cmp::{min, max};
 use iter::CopyingIterator;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 #[derive(Clone)]
 #[derive(Copy)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tokens {
     pub black: u8,
     pub blue: u8,
@@ -139,6 +146,44 @@ impl IndexMut<Color> for Tokens {
     }
 }
 
+// Color codes in `Color::all` order, each preceded by its count if there's more than one, like
+// `2RW`. No tokens is the empty string.
+impl fmt::Display for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for color in Color::all() {
+            match self[color] {
+                0 => (),
+                1 => write!(f, "{}", color.code())?,
+                count => write!(f, "{}{}", count, color.code())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Accepts codes in any order and case. A count applies to the code after it and repeated codes add
+// up, so `RR`, `2r` and `2R` are the same.
+impl FromStr for Tokens {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Tokens, String> {
+        let mut tokens = Tokens::empty();
+        let mut count: Option<u8> = None;
+        for c in s.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit as u8));
+                continue;
+            }
+            let color = Color::from_code(c).ok_or_else(|| format!("unknown color {:?} in {:?}", c, s))?;
+            tokens[color] = tokens[color].saturating_add(count.take().unwrap_or(1));
+        }
+        if count.is_some() {
+            return Err(format!("count without a color in {:?}", s));
+        }
+        Ok(tokens)
+    }
+}
+
 impl AddAssign for Tokens {
     fn add_assign(&mut self, other: Tokens) {
         self.black += other.black;
@@ -212,6 +257,20 @@ mod tests {
         assert_eq!(tokens[Color::White], 5);
     }
 
+    #[test]
+    fn notation() {
+        let mut tokens = Tokens::one(Color::Black);
+        tokens.red = 2;
+        tokens.joker = 1;
+        assert_eq!(tokens.to_string(), "K2RJ");
+        assert_eq!("K2RJ".parse(), Ok(tokens));
+        assert_eq!("rjkr".parse(), Ok(tokens));
+        assert_eq!(Tokens::empty().to_string(), "");
+        assert_eq!("".parse(), Ok(Tokens::empty()));
+        assert!("2".parse::<Tokens>().is_err());
+        assert!("KX".parse::<Tokens>().is_err());
+    }
+
     #[test]
     fn mutate_tokens_with_color() {
         let mut tokens = Tokens {
@@ -0,0 +1,357 @@
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+use rand::thread_rng;
+use agent::{line, search_side_reporting};
+use algo::{SearchConfig, Stop};
+use algo::State as AlgoState;
+use eval::{Weights, WeightedEvaluator};
+use state::State;
+
+// A line based protocol for driving the search from another program, modelled on UCI. Commands:
+//
+//   uci                                    identifies the engine and its options, ends with uciok
+//   isready                                answered with readyok
+//   setoption name NAME value VALUE        a search option of `SearchConfig::set`, or weights FILE
+//   ucinewgame                             deals a new game
+//   position seed N [moves MOVE, MOVE...]  the deal of `State::from_seed`, then the moves
+//   position fen POSITION [moves ...]      a position of `State::to_position`, then the moves
+//   go [depth N] [movetime MS] [nodes N] [infinite]
+//   stop                                   ends the search
+//   d                                      shows the board and its position string
+//   quit
+//
+// Moves are written in move notation and, as that has spaces in it, separated by commas. `go`
+// searches in the background, with alphabeta deepening one ply at a time, and reports every finished
+// depth as
+//
+//   info depth 3 score 12 nodes 5321 time 40 pv take KBG, take 2R, buy 1.2
+//
+// with the score from the side of the player to move. The search ends with `bestmove MOVE`, or
+// `bestmove none` when the game is over. Errors are reported as `info string ...`.
+
+// How deep `go` searches when it's only limited by time or nodes
+pub const MAXIMUM_DEPTH: usize = 64;
+
+// The limits of a `go` command
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct Go {
+    pub depth: Option<usize>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    // Keeps searching until `stop`, and only then reports the best move
+    pub infinite: bool,
+}
+
+impl Go {
+    pub fn parse(words: &[&str]) -> Result<Go, String> {
+        fn number<T: ::std::str::FromStr>(name: &str, value: Option<&&str>) -> Result<T, String> {
+            let value = value.ok_or_else(|| format!("missing a value for {}", name))?;
+            value.parse().map_err(|_| format!("invalid {} {:?}", name, value))
+        }
+
+        let mut go = Go::default();
+        let mut words = words.iter();
+        while let Some(word) = words.next() {
+            match *word {
+                "depth" => {
+                    let depth = number(word, words.next())?;
+                    if depth == 0 {
+                        return Err("depth must be at least 1".to_string());
+                    }
+                    go.depth = Some(depth);
+                },
+                "movetime" => go.movetime = Some(Duration::from_millis(number(word, words.next())?)),
+                "nodes" => go.nodes = Some(number(word, words.next())?),
+                "infinite" => go.infinite = true,
+                _ => return Err(format!("unknown go option {:?}", word)),
+            }
+        }
+        Ok(go)
+    }
+}
+
+// Sets up a position from the words after `position`
+pub fn parse_position(words: &[&str]) -> Result<State, String> {
+    let (setup, moves) = match words.iter().position(|&word| word == "moves") {
+        Some(i) => (&words[..i], &words[i + 1..]),
+        None => (words, &[][..]),
+    };
+    let mut state = match setup.split_first() {
+        Some((&"seed", [seed])) => State::from_seed(2, seed.parse().map_err(|_| format!("invalid seed {:?}", seed))?),
+        Some((&"fen", fields)) => State::from_position(&fields.join(" "))?,
+        _ => return Err("expected position seed N or position fen POSITION".to_string()),
+    };
+    for notation in moves.join(" ").split(',').map(|mov| mov.trim()).filter(|mov| !mov.is_empty()) {
+        if state.is_terminal() {
+            return Err(format!("the game is over before {}", notation));
+        }
+        let mov = state.parse_move(notation)?;
+        state.apply(&mov);
+        state.refill_market();
+    }
+    Ok(state)
+}
+
+// Searches until the depth or a limit is reached, reporting every depth, then reports the best move
+fn run(mut state: State, evaluator: WeightedEvaluator, config: SearchConfig, go: Go, out: Sender<String>) {
+    let limited = go.movetime.is_some() || go.nodes.is_some() || go.infinite;
+    let config = SearchConfig {
+        depth: go.depth.unwrap_or(if limited { MAXIMUM_DEPTH } else { config.depth }),
+        time_limit: go.movetime.or(config.time_limit),
+        node_limit: go.nodes.or(config.node_limit),
+        ..config
+    };
+
+    let best = if state.is_terminal() {
+        None
+    } else {
+        Some(search_side_reporting(&mut state, &evaluator, &config, |result| {
+            let _ = out.send(format!("info depth {} score {} nodes {} time {} pv {}",
+                                     result.depth, result.score, result.nodes, result.elapsed.as_millis(), line(&result.pv)));
+        }))
+    };
+
+    while go.infinite && !config.stop.is_stopped() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mov = best.and_then(|best| config.tie_break.pick(&best.moves, &mut thread_rng()).cloned());
+    let _ = out.send(match mov {
+        Some(mov) => format!("bestmove {}", mov),
+        None => "bestmove none".to_string(),
+    });
+}
+
+struct Searching {
+    stop: Stop,
+    handle: thread::JoinHandle<()>,
+}
+
+pub struct Engine {
+    pub state: State,
+    pub config: SearchConfig,
+    pub weights: Weights,
+    out: Sender<String>,
+    searching: Option<Searching>,
+}
+
+impl Engine {
+    // Replies are sent to `out` a line at a time, also from the search thread
+    pub fn new(out: Sender<String>) -> Engine {
+        Engine {
+            state: State::new(2),
+            config: SearchConfig::default(),
+            weights: Weights::default(),
+            out,
+            searching: None,
+        }
+    }
+
+    fn send<S: Into<String>>(&self, line: S) {
+        let _ = self.out.send(line.into());
+    }
+
+    // Stops the search, if any, and waits for it to report its best move
+    pub fn stop(&mut self) {
+        if let Some(searching) = self.searching.take() {
+            searching.stop.stop();
+            let _ = searching.handle.join();
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching.is_some()
+    }
+
+    fn go(&mut self, go: Go) {
+        self.stop();
+        let stop = Stop::new();
+        let config = SearchConfig {
+            stop: stop.clone(),
+            ..self.config.clone()
+        };
+        let state = self.state.clone();
+        let evaluator = WeightedEvaluator::new(self.weights);
+        let out = self.out.clone();
+        let handle = thread::spawn(move || run(state, evaluator, config, go, out));
+        self.searching = Some(Searching {
+            stop,
+            handle,
+        });
+    }
+
+    fn set_option(&mut self, words: &[&str]) -> Result<(), String> {
+        let (name, value) = match words {
+            ["name", name, "value", value] => (*name, *value),
+            _ => return Err("expected setoption name NAME value VALUE".to_string()),
+        };
+        if name == "weights" {
+            self.weights = Weights::load(value).map_err(|err| format!("couldn't load {}: {}", value, err))?;
+        } else if !self.config.set(name, value)? {
+            return Err(format!("unknown option {}", name));
+        }
+        Ok(())
+    }
+
+    // Handles a line of input, returns false once the engine should quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return true,
+        };
+        let result = match command {
+            "uci" => {
+                self.send("id name splendimax");
+                self.send(format!("option name algorithm type combo default {} var minimax var alphabeta var parallel", self.config.algorithm));
+                self.send(format!("option name depth type spin default {} min 1 max {}", self.config.depth, MAXIMUM_DEPTH));
+                self.send(format!("option name tie-break type combo default {} var first var random", self.config.tie_break));
+                self.send(format!("option name threads type spin default {} min 1 max 64", self.config.threads));
+                self.send(format!("option name prune type check default {}", self.config.prune));
+                self.send("option name weights type string default <empty>");
+                self.send("uciok");
+                Ok(())
+            },
+            "isready" => {
+                self.send("readyok");
+                Ok(())
+            },
+            "setoption" => self.set_option(args),
+            "ucinewgame" => {
+                self.stop();
+                self.state = State::new(2);
+                Ok(())
+            },
+            "position" => {
+                self.stop();
+                parse_position(args).map(|state| self.state = state)
+            },
+            "go" => Go::parse(args).map(|go| self.go(go)),
+            "stop" => {
+                self.stop();
+                Ok(())
+            },
+            "d" => {
+                let mut board = Vec::new();
+                let _ = self.state.print(&mut board);
+                for line in String::from_utf8_lossy(&board).lines() {
+                    self.send(line);
+                }
+                self.send(format!("position {}", self.state.to_position()));
+                Ok(())
+            },
+            "quit" => {
+                self.stop();
+                return false;
+            },
+            _ => Err(format!("unknown command {:?}", command)),
+        };
+        if let Err(err) = result {
+            self.send(format!("info string {}", err));
+        }
+        true
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+
+    // Replies up to and including the first one starting with `last`
+    fn replies(out: &Receiver<String>, last: &str) -> Vec<String> {
+        let mut replies = Vec::new();
+        loop {
+            let reply = out.recv_timeout(Duration::from_secs(30)).expect("no reply");
+            let done = reply.starts_with(last);
+            replies.push(reply);
+            if done {
+                return replies;
+            }
+        }
+    }
+
+    #[test]
+    fn position() {
+        let mut state = State::from_seed(2, 7);
+        let first = state.generate_moves()[0];
+        state.apply(&first);
+        state.refill_market();
+        let second = state.generate_moves()[0];
+        state.apply(&second);
+        state.refill_market();
+
+        let words = format!("seed 7 moves {}, {}", first, second);
+        let words: Vec<&str> = words.split(' ').collect();
+        assert_eq!(parse_position(&words), Ok(state.clone()));
+        let position = format!("fen {}", state.to_position());
+        let words: Vec<&str> = position.split(' ').collect();
+        assert_eq!(parse_position(&words), Ok(state));
+
+        assert!(parse_position(&["seed"]).is_err());
+        assert!(parse_position(&["seed", "x"]).is_err());
+        assert!(parse_position(&["seed", "7", "moves", "buy", "3.9"]).is_err());
+        assert!(parse_position(&["fen", "-"]).is_err());
+    }
+
+    #[test]
+    fn parse_go() {
+        assert_eq!(Go::parse(&[]), Ok(Go::default()));
+        assert_eq!(Go::parse(&["movetime", "2000", "depth", "3"]), Ok(Go {
+            depth: Some(3),
+            movetime: Some(Duration::from_millis(2000)),
+            ..Go::default()
+        }));
+        assert!(Go::parse(&["depth"]).is_err());
+        assert!(Go::parse(&["depth", "0"]).is_err());
+        assert!(Go::parse(&["ponder"]).is_err());
+    }
+
+    #[test]
+    fn search() {
+        let (sender, out) = channel();
+        let mut engine = Engine::new(sender);
+        engine.handle("uci");
+        assert_eq!(replies(&out, "uciok").last().unwrap(), "uciok");
+        engine.handle("setoption name tie-break value first");
+        engine.handle("position seed 3");
+        engine.handle("go depth 3");
+        let replies = replies(&out, "bestmove");
+        let depths: Vec<&str> = replies.iter().filter(|reply| reply.starts_with("info")).filter_map(|reply| reply.split(' ').nth(2)).collect();
+        assert_eq!(depths, vec!["1", "2", "3"]);
+        let mov = replies.last().unwrap().trim_start_matches("bestmove ");
+        assert!(engine.state.parse_move(mov).is_ok());
+
+        // An infinite search only reports its move once stopped
+        engine.handle("go infinite");
+        assert!(engine.is_searching());
+        engine.handle("stop");
+        assert!(!engine.is_searching());
+        assert!(out.try_iter().any(|reply| reply.starts_with("bestmove")));
+
+        engine.handle("go depth x");
+        assert!(out.recv().unwrap().starts_with("info string"));
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    fn game_over() {
+        let (sender, out) = channel();
+        let mut engine = Engine::new(sender);
+        // Five nobles make fifteen points
+        engine.handle("position fen - - - - - - - - -/-/-/4G4R,4G4R,4G4R,4G4R,4G4R -/-/-/- a");
+        engine.handle("isready");
+        assert_eq!(out.recv().unwrap(), "readyok");
+        engine.handle("go movetime 100");
+        assert_eq!(replies(&out, "bestmove"), vec!["bestmove none"]);
+    }
+}
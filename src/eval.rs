@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use algo::Evaluator;
+use cost::Tokens;
+use state::{Score, State, SCORE_TO_WIN};
+
+// The weights of the evaluation function. They can be written to and read from a file with one
+// `name = value` pair per line, lines starting with # are ignored.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub struct Weights {
+    // Per point of difference between the players
+    pub point: Score,
+    // For winning, or losing
+    pub win: Score,
+    // Per card owned
+    pub card: Score,
+    // Per card, times how many of its color the nobles on the board need at most
+    pub noble_color: Score,
+    // Per card, times how many cards of the same color were owned before it, up to 5
+    pub same_color: Score,
+    // Per reserved card
+    pub reserved: Score,
+    // Per token, up to the maximum a player can hold
+    pub token: Score,
+    // Added to `token` for every joker
+    pub joker: Score,
+}
+
+pub const DEFAULT_WEIGHTS: Weights = Weights {
+    point: 3000,
+    win: 1000000,
+    card: 250,
+    noble_color: 100,
+    same_color: 100,
+    reserved: -20,
+    token: 8,
+    // Jokers are worth 50% more than other tokens
+    joker: 4,
+};
+
+impl Default for Weights {
+    fn default() -> Weights {
+        DEFAULT_WEIGHTS
+    }
+}
+
+impl Weights {
+    pub fn names() -> [&'static str; 8] {
+        ["point", "win", "card", "noble_color", "same_color", "reserved", "token", "joker"]
+    }
+
+    pub fn get(&self, name: &str) -> Option<Score> {
+        match name {
+            "point" => Some(self.point),
+            "win" => Some(self.win),
+            "card" => Some(self.card),
+            "noble_color" => Some(self.noble_color),
+            "same_color" => Some(self.same_color),
+            "reserved" => Some(self.reserved),
+            "token" => Some(self.token),
+            "joker" => Some(self.joker),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Score> {
+        match name {
+            "point" => Some(&mut self.point),
+            "win" => Some(&mut self.win),
+            "card" => Some(&mut self.card),
+            "noble_color" => Some(&mut self.noble_color),
+            "same_color" => Some(&mut self.same_color),
+            "reserved" => Some(&mut self.reserved),
+            "token" => Some(&mut self.token),
+            "joker" => Some(&mut self.joker),
+            _ => None,
+        }
+    }
+
+    // Weights that are missing from the input keep their default value. Errors only tell the line,
+    // not what's on it, as the input may not be a weights file at all.
+    pub fn parse(input: &str) -> Result<Weights, String> {
+        let mut weights = Weights::default();
+        for (number, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => return Err(format!("line {}: expected `name = value`", number + 1)),
+            };
+            let value: Score = value.parse().map_err(|_| format!("line {}: invalid value", number + 1))?;
+            match weights.get_mut(name) {
+                Some(weight) => *weight = value,
+                None => return Err(format!("line {}: unknown weight", number + 1)),
+            }
+        }
+        Ok(weights)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Weights> {
+        let mut input = String::new();
+        File::open(path)?.read_to_string(&mut input)?;
+        Weights::parse(&input).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        for name in Weights::names().iter() {
+            writeln!(out, "{} = {}", name, self.get(name).unwrap())?;
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(&mut File::create(path)?)
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct WeightedEvaluator {
+    pub weights: Weights,
+}
+
+// What `algo::State::score` evaluates with
+pub const DEFAULT_EVALUATOR: WeightedEvaluator = WeightedEvaluator {
+    weights: DEFAULT_WEIGHTS,
+};
+
+impl WeightedEvaluator {
+    pub fn new(weights: Weights) -> WeightedEvaluator {
+        WeightedEvaluator {
+            weights,
+        }
+    }
+}
+
+impl Evaluator<State> for WeightedEvaluator {
+    // About one point
+    fn aspiration_window(&self) -> Option<i64> {
+        Some(self.weights.point)
+    }
+
+    fn evaluate(&self, state: &State) -> Score {
+        let weights = &self.weights;
+        let card_multiplier = state.nobles.iter().fold(Tokens::empty(), |acc, noble| acc.max(&noble.cost));
+        let player_score = state.player.score();
+        let adversary_score = state.adversary.score();
+        let mut score = (player_score as Score - adversary_score as Score) * weights.point;
+
+        if player_score >= SCORE_TO_WIN {
+            if player_score < adversary_score {
+                score -= weights.win;
+            } else {
+                score += weights.win;
+            }
+        } else if adversary_score >= SCORE_TO_WIN {
+            score -= weights.win;
+        }
+
+        score += state.player.card_score(&card_multiplier, weights);
+        score -= state.adversary.card_score(&card_multiplier, weights);
+
+        score += state.player.token_score(weights);
+        score -= state.adversary.token_score(weights);
+
+        score += state.player.reserved.len() as Score * weights.reserved;
+        score -= state.adversary.reserved.len() as Score * weights.reserved;
+
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_weights() {
+        let weights = Weights::parse("# tuned\npoint = 2500\n\n  joker=6 \n").unwrap();
+        assert_eq!(weights, Weights {
+            point: 2500,
+            joker: 6,
+            ..Weights::default()
+        });
+        assert!(Weights::parse("points = 1").is_err());
+        assert!(Weights::parse("point 1").is_err());
+        assert!(Weights::parse("point = lots").is_err());
+    }
+
+    #[test]
+    fn write_and_parse_weights() {
+        let weights = Weights {
+            card: 300,
+            reserved: -5,
+            ..Weights::default()
+        };
+        let mut out = Vec::new();
+        weights.write(&mut out).unwrap();
+        assert_eq!(Weights::parse(&String::from_utf8(out).unwrap()), Ok(weights));
+    }
+}
@@ -1,11 +1,24 @@
 #![allow(dead_code)]
 
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "server", all(test, feature = "serde")))]
+extern crate serde_json;
 
+pub mod agent;
 pub mod algo;
+pub mod book;
 pub mod card;
 pub mod color;
 pub mod cost;
+pub mod engine;
+pub mod eval;
 pub mod state;
+pub mod tournament;
 pub mod noble;
+pub mod position;
+pub mod record;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod iter;
\ No newline at end of file
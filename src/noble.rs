@@ -1,4 +1,8 @@
+use std::fmt;
+use std::str::FromStr;
 use cost::Tokens;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 fn create_noble(black: u8, blue: u8, green: u8, red: u8, white: u8) -> Noble {
     Noble {
@@ -16,6 +20,7 @@ fn create_noble(black: u8, blue: u8, green: u8, red: u8, white: u8) -> Noble {
 #[derive(Debug)]
 #[derive(Clone)]
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Noble {
     pub cost: Tokens,
 }
@@ -35,3 +40,20 @@ impl Noble {
         ]
     }
 }
+
+// Nobles are written as their cost, like `4G4R`
+impl fmt::Display for Noble {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.cost)
+    }
+}
+
+impl FromStr for Noble {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Noble, String> {
+        Ok(Noble {
+            cost: s.parse()?,
+        })
+    }
+}
@@ -0,0 +1,232 @@
+use std::str::FromStr;
+use card::Card;
+use color::Color;
+use cost::Tokens;
+use noble::Noble;
+use state::{Player, State, MAXIMUM_COINS, MAXIMUM_RESERVED};
+
+// Points a player may have at most, more than any game gets to while leaving room to add to them
+// without overflowing a u8
+const MAXIMUM_POINTS: u32 = 100;
+
+// A position on one line, for tests, bug reports and puzzles. Fields are separated by spaces:
+//
+//   cards1 cards2 cards3 deck1 deck2 deck3 nobles bank player adversary turn
+//
+// Cards and nobles are written in the notation of their `Display` and separated by commas, decks
+// in the order they're stored in, so the next card dealt is the last one. Tokens are written like
+// `Tokens`. A player is `tokens/cards/reserved/nobles` and the turn is `p` for the player or `a`
+// for the adversary. Empty lists and tokens are written as `-`. A new game looks like
+//
+//   R0:KBG2W,R0:2KG2W,B0:B3GR,K0:2G2W R2:5K3W,... W4:6K3R3W,... R0:2BG,... ... ... 4G4R,3K3R3W,4K4W
+//   4K4B4G4R4W5J -/-/-/- -/-/-/- p
+//
+// but on a single line.
+
+fn list<T: ToString>(items: &[T]) -> String {
+    if items.is_empty() {
+        return "-".to_string();
+    }
+    items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn parse_list<T: FromStr<Err = String>>(field: &str) -> Result<Vec<T>, String> {
+    if field == "-" {
+        return Ok(Vec::new());
+    }
+    field.split(',').map(|item| item.parse()).collect()
+}
+
+fn tokens(tokens: &Tokens) -> String {
+    if tokens.total() == 0 {
+        "-".to_string()
+    } else {
+        tokens.to_string()
+    }
+}
+
+fn parse_tokens(field: &str) -> Result<Tokens, String> {
+    if field == "-" {
+        Ok(Tokens::empty())
+    } else {
+        field.parse()
+    }
+}
+
+fn player(player: &Player) -> String {
+    format!("{}/{}/{}/{}", tokens(&player.tokens), list(&player.cards), list(&player.reserved), list(&player.nobles))
+}
+
+fn parse_player(field: &str) -> Result<Player, String> {
+    let parts: Vec<&str> = field.split('/').collect();
+    if parts.len() != 4 {
+        return Err(format!("expected a player like tokens/cards/reserved/nobles, got {:?}", field));
+    }
+    Ok(Player {
+        tokens: parse_tokens(parts[0])?,
+        cards: parse_list(parts[1])?,
+        reserved: parse_list(parts[2])?,
+        nobles: parse_list(parts[3])?,
+    })
+}
+
+// Checks that a parsed position stays within what the game can hold: at most 4 cards in every row
+// of the market, no more cards, nobles or tokens than there are, cards and nobles that cost no more
+// than the real ones and players that don't hold more than they may. Otherwise the counts could
+// overflow the u8 arithmetic of the rules.
+fn check(state: &State) -> Result<(), String> {
+    let decks = [Card::deck1(), Card::deck2(), Card::deck3()];
+    let real_cards = || decks.iter().flat_map(|deck| deck.iter());
+    let most = |costs: &mut dyn Iterator<Item = Tokens>| {
+        costs.fold(Tokens::empty(), |most, cost| most.max(&cost))
+    };
+    let most_card_cost = most(&mut real_cards().map(|card| card.cost));
+    let most_noble_cost = most(&mut Noble::all().into_iter().map(|noble| noble.cost));
+    let most_points = real_cards().map(|card| card.points).max().unwrap_or(0);
+    let within = |most: &Tokens, cost: &Tokens| Color::all().all(|color| cost[color] <= most[color]);
+
+    for (row, cards) in [&state.cards1, &state.cards2, &state.cards3].iter().enumerate() {
+        if cards.len() > 4 {
+            return Err(format!("market row {} has {} cards, at most 4 fit", row + 1, cards.len()));
+        }
+    }
+    let players = [&state.player, &state.adversary];
+    let cards: Vec<&Card> = [&state.cards1, &state.cards2, &state.cards3, &state.deck1, &state.deck2, &state.deck3].iter()
+        .flat_map(|cards| cards.iter())
+        .chain(players.iter().flat_map(|player| player.cards.iter().chain(player.reserved.iter())))
+        .collect();
+    if cards.len() > real_cards().count() {
+        return Err(format!("{} cards, the game only has {}", cards.len(), real_cards().count()));
+    }
+    if let Some(card) = cards.iter().find(|card| card.points > most_points || !within(&most_card_cost, &card.cost)) {
+        return Err(format!("card {} is worth or costs more than any card of the game", card));
+    }
+    let nobles: Vec<&Noble> = state.nobles.iter().chain(players.iter().flat_map(|player| player.nobles.iter())).collect();
+    if nobles.len() > Noble::all().len() {
+        return Err(format!("{} nobles, the game only has {}", nobles.len(), Noble::all().len()));
+    }
+    if let Some(noble) = nobles.iter().find(|noble| !within(&most_noble_cost, &noble.cost)) {
+        return Err(format!("noble {} costs more than any noble of the game", noble));
+    }
+
+    let start = Tokens::start(2);
+    for color in Color::all() {
+        let total = state.bank[color] as u32 + players.iter().map(|player| player.tokens[color] as u32).sum::<u32>();
+        if total > start[color] as u32 {
+            return Err(format!("{} {} tokens, the game only has {}", total, color.code(), start[color]));
+        }
+    }
+    for player in players.iter() {
+        if player.tokens.total() > MAXIMUM_COINS {
+            return Err(format!("a player has {} tokens, at most {} are allowed", player.tokens.total(), MAXIMUM_COINS));
+        }
+        if player.reserved.len() > MAXIMUM_RESERVED {
+            return Err(format!("a player reserved {} cards, at most {} are allowed", player.reserved.len(), MAXIMUM_RESERVED));
+        }
+        let points = player.cards.iter().map(|card| card.points as u32).sum::<u32>() + player.nobles.len() as u32 * 3;
+        if points > MAXIMUM_POINTS {
+            return Err(format!("a player has {} points, at most {} are allowed", points, MAXIMUM_POINTS));
+        }
+    }
+    Ok(())
+}
+
+impl State {
+    pub fn to_position(&self) -> String {
+        let fields = [
+            list(&self.cards1),
+            list(&self.cards2),
+            list(&self.cards3),
+            list(&self.deck1),
+            list(&self.deck2),
+            list(&self.deck3),
+            list(&self.nobles),
+            tokens(&self.bank),
+            player(&self.player),
+            player(&self.adversary),
+            (if self.players_turn { "p" } else { "a" }).to_string(),
+        ];
+        fields.join(" ")
+    }
+
+    // The inverse of `to_position`. The position isn't checked for being reachable, so puzzles can
+    // leave out cards that don't matter, only for fitting in the game, see `check`.
+    pub fn from_position(position: &str) -> Result<State, String> {
+        let fields: Vec<&str> = position.split_whitespace().collect();
+        if fields.len() != 11 {
+            return Err(format!("expected 11 fields, got {}", fields.len()));
+        }
+        let cards = |field: &str| -> Result<Vec<Card>, String> { parse_list(field) };
+        let nobles: Vec<Noble> = parse_list(fields[6])?;
+        let state = State {
+            cards1: cards(fields[0])?,
+            cards2: cards(fields[1])?,
+            cards3: cards(fields[2])?,
+            deck1: cards(fields[3])?,
+            deck2: cards(fields[4])?,
+            deck3: cards(fields[5])?,
+            nobles,
+            bank: parse_tokens(fields[7])?,
+            player: parse_player(fields[8])?,
+            adversary: parse_player(fields[9])?,
+            players_turn: match fields[10] {
+                "p" => true,
+                "a" => false,
+                turn => return Err(format!("expected p or a for the turn, got {:?}", turn)),
+            },
+        };
+        check(&state)?;
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algo::State as AlgoState;
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn round_trip() {
+        let mut rng = thread_rng();
+        let mut state = State::new(2);
+        let mut plies = 0;
+        // Random players can end up passing forever
+        while !state.is_terminal() && plies < 200 {
+            let position = state.to_position();
+            assert_eq!(position.split(' ').count(), 11);
+            assert_eq!(State::from_position(&position), Ok(state.clone()));
+            let mov = *rng.choose(&state.generate_moves()).unwrap();
+            state.apply(&mov);
+            state.refill_market();
+            plies += 1;
+        }
+    }
+
+    #[test]
+    fn parse_puzzle() {
+        let state = State::from_position("K1:4B - - - - - 4G4R 4K1B4G4R4W4J 3BJ/B0:3K/-/- -/-/-/- p").unwrap();
+        assert_eq!(state.cards1.len(), 1);
+        assert!(state.deck1.is_empty());
+        assert_eq!(state.player.tokens.joker, 1);
+        assert_eq!(state.player.cards.len(), 1);
+        assert_eq!(state.parse_move("buy 1.1").map(|mov| mov.to_string()), Ok("buy 1.1".to_string()));
+
+        assert!(State::from_position("- - - - - - - - -/-/-/- -/-/-/-").is_err());
+        assert!(State::from_position("- - - - - - - - -/-/- -/-/-/- p").is_err());
+        assert!(State::from_position("X1:4B - - - - - - - -/-/-/- -/-/-/- p").is_err());
+        assert!(State::from_position("- - - - - - - - -/-/-/- -/-/-/- x").is_err());
+    }
+
+    #[test]
+    fn reject_impossible_positions() {
+        let nobles = vec!["4G4R"; 90].join(",");
+        assert!(State::from_position(&format!("- - - - - - - - -/-/-/{} -/-/-/- a", nobles)).is_err());
+        assert!(State::from_position("- - - - - - - - -/-/-/- -/K255:4B/-/- a").is_err());
+        assert!(State::from_position("K1:4B,K1:4B,K1:4B,K1:4B,K1:4B - - - - - - - -/-/-/- -/-/-/- p").is_err());
+        assert!(State::from_position("- - - - - - - - -/-/K1:4B,K1:4B,K1:4B,K1:4B/- -/-/-/- p").is_err());
+        assert!(State::from_position("- - - - - - - 4B 1B/-/-/- -/-/-/- p").is_err());
+        assert!(State::from_position("- - - - - - - - 2K2B2G2R2W1J/-/-/- -/-/-/- p").is_err());
+        assert!(State::from_position("- - - - - - - - -/-/-/- -/-/-/- p").is_ok());
+    }
+}
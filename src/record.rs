@@ -0,0 +1,287 @@
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use algo::{Endgame, Outcome};
+use algo::State as AlgoState;
+use card::Card;
+use noble::Noble;
+use state::{Move, State, MAXIMUM_COINS, MAXIMUM_RESERVED, SCORE_TO_WIN};
+
+// The only format written so far, records with a newer one are refused
+pub const VERSION: u32 = 1;
+
+// How the cards and nobles were dealt
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Setup {
+    // For `State::from_seed`, which only deals the same cards with the same version of rand
+    Seed(u64),
+    // The decks in the order `State::from_decks` takes them, so with the market at the end
+    Decks {
+        deck1: Vec<Card>,
+        deck2: Vec<Card>,
+        deck3: Vec<Card>,
+        nobles: Vec<Noble>,
+    },
+}
+
+impl Setup {
+    // The setup of a game that hasn't started yet
+    pub fn from_state(state: &State) -> Setup {
+        let deck = |deck: &Vec<Card>, cards: &Vec<Card>| deck.iter().chain(cards.iter()).cloned().collect();
+        Setup::Decks {
+            deck1: deck(&state.deck1, &state.cards1),
+            deck2: deck(&state.deck2, &state.cards2),
+            deck3: deck(&state.deck3, &state.cards3),
+            nobles: state.nobles.clone(),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        match *self {
+            Setup::Seed(seed) => State::from_seed(2, seed),
+            Setup::Decks { ref deck1, ref deck2, ref deck3, ref nobles } => State::from_decks(2, deck1.clone(), deck2.clone(), deck3.clone(), nobles.clone()),
+        }
+    }
+}
+
+// The rules the game was played with. They're fixed at compile time, the line only makes sure a
+// record isn't replayed with different ones.
+fn rules() -> String {
+    format!("players 2 score {} tokens {} reserved {}", SCORE_TO_WIN, MAXIMUM_COINS, MAXIMUM_RESERVED)
+}
+
+fn result(state: &State) -> &'static str {
+    match state.outcome() {
+        Some(Outcome::Win) => "player",
+        Some(Outcome::Loss) => "adversary",
+        Some(Outcome::Draw) => "draw",
+        None => "unfinished",
+    }
+}
+
+// A game from the start, for replaying it later. Written as lines of a keyword and its value:
+//
+//   version 1
+//   rules players 2 score 15 tokens 10 reserved 3
+//   player alphabeta --depth 6
+//   adversary human
+//   seed 1234
+//   moves
+//   take KBG
+//   buy 1.2
+//   result unfinished
+//
+// Instead of `seed` there can be `deck1`, `deck2`, `deck3` and `nobles` lines with the cards and
+// nobles in the notation of their `Display`. Moves follow `moves`, one per line, and `result` is
+// who won after the last one. Lines starting with # are ignored.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct GameRecord {
+    pub setup: Setup,
+    // Who played each side, like the options of a bot
+    pub player: String,
+    pub adversary: String,
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    pub fn new(setup: Setup, player: &str, adversary: &str) -> GameRecord {
+        GameRecord {
+            setup,
+            player: player.to_string(),
+            adversary: adversary.to_string(),
+            moves: Vec::new(),
+        }
+    }
+
+    // Plays the moves from the setup, refilling the market after each like a game does
+    pub fn replay(&self) -> Result<State, String> {
+        let mut state = self.setup.state();
+        for (i, mov) in self.moves.iter().enumerate() {
+            if state.is_terminal() || !state.generate_moves().contains(mov) {
+                return Err(format!("move {} ({}) isn't legal", i + 1, mov));
+            }
+            state.apply(mov);
+            state.refill_market();
+        }
+        Ok(state)
+    }
+
+    pub fn parse(input: &str) -> Result<GameRecord, String> {
+        let mut version = None;
+        let mut player = None;
+        let mut adversary = None;
+        let mut seed = None;
+        let mut decks: [Option<Vec<Card>>; 3] = [None, None, None];
+        let mut nobles = None;
+        // Moves are parsed after the setup is known, since the notation leaves out what follows
+        // from the position
+        let mut moves = Vec::new();
+        let mut in_moves = false;
+        let mut result = None;
+
+        for (number, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |err: String| format!("line {}: {}", number + 1, err);
+            let mut parts = line.splitn(2, ' ');
+            let keyword = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            match keyword {
+                "version" => version = Some(value.parse::<u32>().map_err(|_| error(format!("invalid version {:?}", value)))?),
+                "rules" if value != rules() => return Err(error(format!("played with rules {:?}, expected {:?}", value, rules()))),
+                "rules" => (),
+                "player" => player = Some(value.to_string()),
+                "adversary" => adversary = Some(value.to_string()),
+                "seed" => seed = Some(value.parse::<u64>().map_err(|_| error(format!("invalid seed {:?}", value)))?),
+                "deck1" | "deck2" | "deck3" => {
+                    let cards: Result<Vec<Card>, String> = value.split_whitespace().map(|card| card.parse()).collect();
+                    let index = (keyword.as_bytes()[4] - b'1') as usize;
+                    decks[index] = Some(cards.map_err(&error)?);
+                },
+                "nobles" => {
+                    let parsed: Result<Vec<Noble>, String> = value.split_whitespace().map(|noble| noble.parse()).collect();
+                    nobles = Some(parsed.map_err(&error)?);
+                },
+                "moves" => in_moves = true,
+                "result" => result = Some(value.to_string()),
+                _ if in_moves && result.is_none() => moves.push((number, line)),
+                _ => return Err(error(format!("unexpected {:?}", keyword))),
+            }
+        }
+
+        match version {
+            Some(VERSION) => (),
+            Some(version) => return Err(format!("version {} isn't supported, expected {}", version, VERSION)),
+            None => return Err("missing version".to_string()),
+        }
+        let setup = match (seed, decks, nobles) {
+            (Some(seed), [None, None, None], None) => Setup::Seed(seed),
+            (None, [Some(deck1), Some(deck2), Some(deck3)], Some(nobles)) => Setup::Decks { deck1, deck2, deck3, nobles },
+            _ => return Err("expected either a seed or all of deck1, deck2, deck3 and nobles".to_string()),
+        };
+
+        let mut record = GameRecord::new(setup, &player.unwrap_or_default(), &adversary.unwrap_or_default());
+        let mut state = record.setup.state();
+        for (number, line) in moves.into_iter() {
+            if state.is_terminal() {
+                return Err(format!("line {}: the game is over", number + 1));
+            }
+            let mov = state.parse_move(line).map_err(|err| format!("line {}: {}", number + 1, err))?;
+            state.apply(&mov);
+            state.refill_market();
+            record.moves.push(mov);
+        }
+        if let Some(result) = result {
+            if result != self::result(&state) {
+                return Err(format!("the record says the result is {} but replaying it gives {}", result, self::result(&state)));
+            }
+        }
+        Ok(record)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<GameRecord> {
+        let mut input = String::new();
+        File::open(path)?.read_to_string(&mut input)?;
+        GameRecord::parse(&input).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        fn line<T: ToString>(out: &mut dyn Write, keyword: &str, items: &[T]) -> io::Result<()> {
+            let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+            writeln!(out, "{} {}", keyword, items.join(" "))
+        }
+
+        writeln!(out, "version {}", VERSION)?;
+        writeln!(out, "rules {}", rules())?;
+        writeln!(out, "player {}", self.player)?;
+        writeln!(out, "adversary {}", self.adversary)?;
+        match self.setup {
+            Setup::Seed(seed) => writeln!(out, "seed {}", seed)?,
+            Setup::Decks { ref deck1, ref deck2, ref deck3, ref nobles } => {
+                line(out, "deck1", deck1)?;
+                line(out, "deck2", deck2)?;
+                line(out, "deck3", deck3)?;
+                line(out, "nobles", nobles)?;
+            },
+        }
+        writeln!(out, "moves")?;
+        for mov in self.moves.iter() {
+            writeln!(out, "{}", mov)?;
+        }
+        // Moves that weren't legal are still written, but get no result
+        if let Ok(state) = self.replay() {
+            writeln!(out, "result {}", result(&state))?;
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(&mut File::create(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    // The record of the game and the state it ended in
+    fn random_game(setup: Setup) -> (GameRecord, State) {
+        let mut rng = thread_rng();
+        let mut record = GameRecord::new(setup, "random", "random");
+        let mut state = record.setup.state();
+        // Random players can end up passing forever, which leaves the game unfinished
+        while !state.is_terminal() && record.moves.len() < 200 {
+            let mov = *rng.choose(&state.generate_moves()).unwrap();
+            state.apply(&mov);
+            state.refill_market();
+            record.moves.push(mov);
+        }
+        (record, state)
+    }
+
+    fn round_trip(record: &GameRecord) -> GameRecord {
+        let mut out = Vec::new();
+        record.write(&mut out).unwrap();
+        GameRecord::parse(&String::from_utf8(out).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn replay_from_seed() {
+        assert_eq!(State::from_seed(2, 7), State::from_seed(2, 7));
+        let (record, played) = random_game(Setup::Seed(7));
+        let parsed = round_trip(&record);
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.replay(), Ok(played));
+    }
+
+    #[test]
+    fn replay_from_decks() {
+        let state = State::new(2);
+        let setup = Setup::from_state(&state);
+        assert_eq!(setup.state(), state);
+        let (record, played) = random_game(setup);
+        let parsed = round_trip(&record);
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.replay(), Ok(played));
+    }
+
+    #[test]
+    fn refuse_broken_records() {
+        let header = "version 1\nrules players 2 score 15 tokens 10 reserved 3\nseed 3\n";
+        assert!(GameRecord::parse(header).is_ok());
+        assert!(GameRecord::parse("version 2\nseed 3\n").is_err());
+        assert!(GameRecord::parse("version 1\nrules players 2 score 21 tokens 10 reserved 3\nseed 3\n").is_err());
+        assert!(GameRecord::parse("version 1\n").is_err());
+        assert!(GameRecord::parse(&format!("{}moves\nbuyres 1\n", header)).is_err());
+        assert!(GameRecord::parse(&format!("{}moves\ntake KBG\nresult player\n", header)).is_err());
+        assert!(GameRecord::parse(&format!("{}moves\ntake KBG\nresult unfinished\n", header)).is_ok());
+    }
+}
@@ -0,0 +1,551 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json;
+use agent::{search_side, Kind};
+use algo::{Endgame, Outcome};
+use algo::State as AlgoState;
+use eval::WeightedEvaluator;
+use state::{Move, State};
+use tournament::Bot;
+
+// A small HTTP server with a JSON API for frontends, games are kept in memory until the server
+// exits. Bodies are JSON, moves are sent in text notation and games are returned as a `Game`.
+//
+//   POST   /games                 {"seed": 42} or {"position": "..."}, both optional, a new game
+//   GET    /games/ID              the game
+//   DELETE /games/ID              forgets the game
+//   GET    /games/ID/moves        the legal moves, as `LegalMove`s
+//   POST   /games/ID/moves        {"move": "take KBG"}, plays the move
+//   POST   /games/ID/bot          {"bot": "greedy"}, optional, the bot plays a move
+//   POST   /games/ID/analysis     {"bot": "--depth 8"}, optional, searches without playing
+//
+// Bots are described like `tournament::Bot::parse`, without one the server's bot is used. They
+// can't load weights from files and their search is held to `MAXIMUM_DEPTH`, `MAXIMUM_THREADS` and
+// `MAXIMUM_TIME`. Errors are returned as {"error": "..."} with a 4xx status.
+//
+// Games never expire, they're kept until deleted, and every connection gets a thread of its own
+// without a limit on how many run at once. The server is meant for a frontend on a trusted network,
+// put it behind a proxy that limits connections before exposing it.
+
+// Larger request bodies are refused
+pub const MAXIMUM_BODY: usize = 1 << 16;
+
+// What the bots of requests may search with at most, so one request can't take over the server
+pub const MAXIMUM_DEPTH: usize = 10;
+pub const MAXIMUM_THREADS: usize = 4;
+pub const MAXIMUM_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Response {
+        Response {
+            status,
+            body: serde_json::to_string(value).expect("couldn't serialize a response"),
+        }
+    }
+
+    fn error<S: Into<String>>(status: u16, error: S) -> Response {
+        Response::json(status, &Error {
+            error: error.into(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Error {
+    error: String,
+}
+
+#[derive(Deserialize)]
+#[derive(Default)]
+#[serde(default)]
+struct NewGame {
+    seed: Option<u64>,
+    position: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Play {
+    #[serde(rename = "move")]
+    mov: String,
+}
+
+#[derive(Deserialize)]
+#[derive(Default)]
+#[serde(default)]
+struct Think {
+    bot: Option<String>,
+}
+
+// The view of a game that every endpoint changing it returns
+#[derive(Serialize)]
+pub struct Game<'a> {
+    pub id: u64,
+    pub state: &'a State,
+    // The one line position of `State::to_position`
+    pub position: String,
+    // player or adversary
+    pub turn: &'static str,
+    // player, adversary or draw once the game is over
+    pub winner: Option<&'static str>,
+    pub points: Points,
+    // The moves played so far, in text notation
+    pub history: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Points {
+    pub player: u8,
+    pub adversary: u8,
+}
+
+#[derive(Serialize)]
+pub struct LegalMove {
+    pub notation: String,
+    #[serde(rename = "move")]
+    pub mov: Move,
+}
+
+#[derive(Serialize)]
+pub struct BotMove<'a> {
+    #[serde(rename = "move")]
+    pub mov: String,
+    pub game: Game<'a>,
+}
+
+// A search from the side to move, which the score is from
+#[derive(Serialize)]
+pub struct Analysis {
+    pub score: i64,
+    pub depth: usize,
+    pub nodes: u64,
+    // In milliseconds
+    pub time: u64,
+    // All moves that share the best score
+    pub best: Vec<String>,
+    pub pv: Vec<String>,
+}
+
+struct Session {
+    state: State,
+    moves: Vec<Move>,
+}
+
+impl Session {
+    fn game<'a>(&'a self, id: u64) -> Game<'a> {
+        let state = &self.state;
+        Game {
+            id,
+            state,
+            position: state.to_position(),
+            turn: if state.players_turn { "player" } else { "adversary" },
+            winner: state.outcome().map(|outcome| match outcome {
+                Outcome::Win => "player",
+                Outcome::Loss => "adversary",
+                Outcome::Draw => "draw",
+            }),
+            points: Points {
+                player: state.player.score(),
+                adversary: state.adversary.score(),
+            },
+            history: self.moves.iter().map(|mov| mov.to_string()).collect(),
+        }
+    }
+
+    fn play(&mut self, mov: Move) {
+        self.state.apply(&mov);
+        self.state.refill_market();
+        self.moves.push(mov);
+    }
+}
+
+#[derive(Default)]
+struct Sessions {
+    next: u64,
+    games: HashMap<u64, Session>,
+}
+
+pub struct Server {
+    // Plays and analyses when a request doesn't ask for another bot
+    pub bot: Bot,
+    sessions: Mutex<Sessions>,
+}
+
+fn parse<T: DeserializeOwned + Default>(body: &str) -> Result<T, Response> {
+    if body.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(body).map_err(|err| Response::error(400, format!("invalid request: {}", err)))
+}
+
+impl Server {
+    pub fn new(bot: Bot) -> Server {
+        Server {
+            bot,
+            sessions: Mutex::new(Sessions::default()),
+        }
+    }
+
+    // Answers a request, `path` without the query string
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Response {
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let result = match (method, &parts[..]) {
+            ("POST", ["games"]) => self.new_game(body),
+            (_, ["games", id, rest @ ..]) => match id.parse() {
+                Ok(id) => match (method, rest) {
+                    ("GET", []) => self.get(id),
+                    ("DELETE", []) => self.delete(id),
+                    ("GET", ["moves"]) => self.moves(id),
+                    ("POST", ["moves"]) => self.play(id, body),
+                    ("POST", ["bot"]) => self.bot_move(id, body),
+                    ("POST", ["analysis"]) => self.analyse(id, body),
+                    _ => Err(Response::error(404, format!("no {} {}", method, path))),
+                },
+                Err(_) => Err(Response::error(404, format!("invalid game {:?}", id))),
+            },
+            _ => Err(Response::error(404, format!("no {} {}", method, path))),
+        };
+        result.unwrap_or_else(|response| response)
+    }
+
+    // The sessions, still usable after a request panicked while holding them, at worst the game it
+    // was changing is left half changed
+    fn sessions<'a>(&'a self) -> MutexGuard<'a, Sessions> {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Calls `f` with the session of game `id`
+    fn with_session<T, F: FnOnce(&mut Session) -> Result<T, Response>>(&self, id: u64, f: F) -> Result<T, Response> {
+        let mut sessions = self.sessions();
+        match sessions.games.get_mut(&id) {
+            Some(session) => f(session),
+            None => Err(Response::error(404, format!("no game {}", id))),
+        }
+    }
+
+    fn new_game(&self, body: &str) -> Result<Response, Response> {
+        let request: NewGame = parse(body)?;
+        let state = match (request.seed, request.position) {
+            (Some(_), Some(_)) => return Err(Response::error(400, "expected a seed or a position, not both")),
+            (Some(seed), None) => State::from_seed(2, seed),
+            (None, Some(position)) => State::from_position(&position).map_err(|err| Response::error(400, err))?,
+            (None, None) => State::new(2),
+        };
+        let session = Session {
+            state,
+            moves: Vec::new(),
+        };
+        let id = {
+            let mut sessions = self.sessions();
+            sessions.next += 1;
+            sessions.next
+        };
+        // The view is built before taking the sessions again, so nothing about the new game can
+        // panic while they're held
+        let response = Response::json(201, &session.game(id));
+        self.sessions().games.insert(id, session);
+        Ok(response)
+    }
+
+    fn get(&self, id: u64) -> Result<Response, Response> {
+        self.with_session(id, |session| Ok(Response::json(200, &session.game(id))))
+    }
+
+    fn delete(&self, id: u64) -> Result<Response, Response> {
+        match self.sessions().games.remove(&id) {
+            Some(_) => Ok(Response {
+                status: 204,
+                body: String::new(),
+            }),
+            None => Err(Response::error(404, format!("no game {}", id))),
+        }
+    }
+
+    fn moves(&self, id: u64) -> Result<Response, Response> {
+        self.with_session(id, |session| {
+            let moves: Vec<LegalMove> = if session.state.is_terminal() {
+                Vec::new()
+            } else {
+                session.state.generate_moves().into_iter().map(|mov| LegalMove {
+                    notation: mov.to_string(),
+                    mov,
+                }).collect()
+            };
+            Ok(Response::json(200, &moves))
+        })
+    }
+
+    fn play(&self, id: u64, body: &str) -> Result<Response, Response> {
+        let request: Play = serde_json::from_str(body).map_err(|err| Response::error(400, format!("invalid request: {}", err)))?;
+        self.with_session(id, |session| {
+            if session.state.is_terminal() {
+                return Err(Response::error(409, "the game is over"));
+            }
+            let mov = session.state.parse_move(&request.mov).map_err(|err| Response::error(400, err))?;
+            session.play(mov);
+            Ok(Response::json(200, &session.game(id)))
+        })
+    }
+
+    fn bot(&self, body: &str) -> Result<Bot, Response> {
+        let request: Think = parse(body)?;
+        let mut bot = match request.bot {
+            Some(spec) => Bot::parse_without_files(&spec).map_err(|err| Response::error(400, err))?,
+            None => return Ok(self.bot.clone()),
+        };
+        bot.search.depth = cmp::min(bot.search.depth, MAXIMUM_DEPTH);
+        bot.search.threads = cmp::min(bot.search.threads, MAXIMUM_THREADS);
+        bot.search.time_limit = Some(bot.search.time_limit.map_or(MAXIMUM_TIME, |time_limit| cmp::min(time_limit, MAXIMUM_TIME)));
+        Ok(bot)
+    }
+
+    // The position of game `id` and how many moves were played to reach it, searches run on a copy
+    // so other requests aren't kept waiting
+    fn position(&self, id: u64) -> Result<(State, usize), Response> {
+        self.with_session(id, |session| {
+            if session.state.is_terminal() {
+                return Err(Response::error(409, "the game is over"));
+            }
+            Ok((session.state.clone(), session.moves.len()))
+        })
+    }
+
+    fn bot_move(&self, id: u64, body: &str) -> Result<Response, Response> {
+        let bot = self.bot(body)?;
+        let (state, plies) = self.position(id)?;
+        let mov = bot.agent(thread_rng().gen()).choose(&state).ok_or_else(|| Response::error(409, "the bot has no move"))?;
+        self.with_session(id, |session| {
+            if session.moves.len() != plies {
+                return Err(Response::error(409, "the game changed while the bot was thinking"));
+            }
+            session.play(mov);
+            Ok(Response::json(200, &BotMove {
+                mov: mov.to_string(),
+                game: session.game(id),
+            }))
+        })
+    }
+
+    fn analyse(&self, id: u64, body: &str) -> Result<Response, Response> {
+        let bot = self.bot(body)?;
+        if bot.kind != Kind::Search {
+            return Err(Response::error(400, "only search bots can analyse"));
+        }
+        let (mut state, _) = self.position(id)?;
+        let result = search_side(&mut state, &WeightedEvaluator::new(bot.weights), &bot.search);
+        Ok(Response::json(200, &Analysis {
+            score: result.score,
+            depth: result.depth,
+            nodes: result.nodes,
+            time: result.elapsed.as_millis() as u64,
+            best: result.moves.iter().map(|mov| mov.to_string()).collect(),
+            pv: result.pv.iter().map(|mov| mov.to_string()).collect(),
+        }))
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "",
+    }
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    // Frontends are usually served from another origin
+    write!(stream, "HTTP/1.1 {} {}\r\n\
+                    Content-Type: application/json\r\n\
+                    Content-Length: {}\r\n\
+                    Access-Control-Allow-Origin: *\r\n\
+                    Access-Control-Allow-Methods: GET, POST, DELETE\r\n\
+                    Access-Control-Allow-Headers: Content-Type\r\n\
+                    Connection: close\r\n\r\n{}",
+           response.status, reason(response.status), response.body.len(), response.body)?;
+    stream.flush()
+}
+
+// Reads a single request and answers it
+fn connection(server: &Server, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let method = words.next().unwrap_or("").to_string();
+    let target = words.next().unwrap_or("").to_string();
+    let path = target.split('?').next().unwrap_or("");
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = parts.next().unwrap_or("").trim().parse().unwrap_or(0);
+        }
+    }
+
+    let response = if method == "OPTIONS" {
+        // A CORS preflight, the headers are in every response
+        Response {
+            status: 204,
+            body: String::new(),
+        }
+    } else if length > MAXIMUM_BODY {
+        Response::error(413, format!("request bodies can't be over {} bytes", MAXIMUM_BODY))
+    } else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        match String::from_utf8(body) {
+            Ok(body) => server.handle(&method, path, &body),
+            Err(_) => Response::error(400, "the body isn't UTF-8"),
+        }
+    };
+    write_response(&mut stream, &response)
+}
+
+// Answers requests on `listener` until accepting a connection fails, every connection on its own
+// thread
+pub fn serve(server: Arc<Server>, listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = server.clone();
+        thread::spawn(move || {
+            let _ = connection(&server, stream);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let response = server.handle(method, path, body);
+        let value = if response.body.is_empty() { Value::Null } else { serde_json::from_str(&response.body).unwrap() };
+        (response.status, value)
+    }
+
+    #[test]
+    fn play_a_game() {
+        let server = Server::new(Bot::parse("--depth 2").unwrap());
+        let (status, game) = request(&server, "POST", "/games", r#"{"seed": 5}"#);
+        assert_eq!(status, 201);
+        assert_eq!(game["id"], 1);
+        assert_eq!(game["turn"], "player");
+        assert_eq!(game["position"], State::from_seed(2, 5).to_position());
+
+        let (status, moves) = request(&server, "GET", "/games/1/moves", "");
+        assert_eq!(status, 200);
+        let moves = moves.as_array().unwrap();
+        assert_eq!(moves.len(), State::from_seed(2, 5).generate_moves().len());
+        let notation = moves[0]["notation"].as_str().unwrap().to_string();
+        assert_eq!(moves[0]["move"]["type"], "take");
+
+        let (status, game) = request(&server, "POST", "/games/1/moves", &format!(r#"{{"move": "{}"}}"#, notation));
+        assert_eq!(status, 200);
+        assert_eq!(game["history"], Value::from(vec![notation.clone()]));
+        assert_eq!(game["turn"], "adversary");
+
+        let (status, analysis) = request(&server, "POST", "/games/1/analysis", r#"{"bot": "--depth 1"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(analysis["depth"], 1);
+        assert!(!analysis["best"].as_array().unwrap().is_empty());
+
+        let (status, played) = request(&server, "POST", "/games/1/bot", r#"{"bot": "greedy"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(played["game"]["history"][1], played["move"]);
+        let (status, played) = request(&server, "POST", "/games/1/bot", "");
+        assert_eq!(status, 200);
+        assert_eq!(request(&server, "GET", "/games/1", "").1, played["game"]);
+
+        assert_eq!(request(&server, "DELETE", "/games/1", "").0, 204);
+        assert_eq!(request(&server, "GET", "/games/1", "").0, 404);
+    }
+
+    #[test]
+    fn refuse_bad_requests() {
+        let server = Server::new(Bot::parse("").unwrap());
+        assert_eq!(request(&server, "GET", "/games/1", "").0, 404);
+        assert_eq!(request(&server, "GET", "/games/x", "").0, 404);
+        assert_eq!(request(&server, "GET", "/nothing", "").0, 404);
+        assert_eq!(request(&server, "POST", "/games", "{").0, 400);
+        assert_eq!(request(&server, "POST", "/games", r#"{"position": "-"}"#).0, 400);
+        let nobles = vec!["4G4R"; 90].join(",");
+        let (status, error) = request(&server, "POST", "/games", &format!(r#"{{"position": "- - - - - - - - -/-/-/{} -/-/-/- a"}}"#, nobles));
+        assert_eq!(status, 400);
+        assert_eq!(error["error"], "90 nobles, the game only has 9");
+
+        // Five nobles make fifteen points
+        let (status, game) = request(&server, "POST", "/games", r#"{"position": "- - - - - - - - -/-/-/4G4R,4G4R,4G4R,4G4R,4G4R -/-/-/- a"}"#);
+        assert_eq!(status, 201);
+        assert_eq!(game["winner"], "player");
+        assert_eq!(request(&server, "POST", "/games/1/moves", r#"{"move": "pass"}"#).0, 409);
+        assert_eq!(request(&server, "POST", "/games/1/bot", "").0, 409);
+
+        request(&server, "POST", "/games", r#"{"seed": 1}"#);
+        let (status, error) = request(&server, "POST", "/games/2/moves", r#"{"move": "buy 3.9"}"#);
+        assert_eq!(status, 400);
+        assert!(error["error"].is_string());
+        assert_eq!(request(&server, "POST", "/games/2/moves", r#"{"mov": "pass"}"#).0, 400);
+        assert_eq!(request(&server, "POST", "/games/2/bot", r#"{"bot": "--depth"}"#).0, 400);
+        assert_eq!(request(&server, "POST", "/games/2/analysis", r#"{"bot": "random"}"#).0, 400);
+
+        // Request bots can't read files and only search so much
+        let (status, error) = request(&server, "POST", "/games/2/analysis", r#"{"bot": "--weights /proc/self/environ"}"#);
+        assert_eq!(status, 400);
+        assert_eq!(error["error"], "--weights isn't allowed here");
+        let bot = server.bot(r#"{"bot": "--depth 40 --threads 64 --time 3600000"}"#).unwrap();
+        assert_eq!((bot.search.depth, bot.search.threads, bot.search.time_limit), (MAXIMUM_DEPTH, MAXIMUM_THREADS, Some(MAXIMUM_TIME)));
+        assert_eq!(server.bot(r#"{"bot": "--depth 2"}"#).unwrap().search.time_limit, Some(MAXIMUM_TIME));
+    }
+
+    #[test]
+    fn serve_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(Bot::parse("").unwrap()));
+        thread::spawn(move || serve(server, listener));
+
+        let body = r#"{"seed": 3}"#;
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "POST /games?pretty HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        let game: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(game["position"], State::from_seed(2, 3).to_position());
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /games/2 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
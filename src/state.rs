@@ -1,23 +1,36 @@
 use card::Card;
 use color::Color;
 use cost::Tokens;
-use std::cmp::min;
+use std::cmp;
+use std::cmp::{min, Ordering, Reverse};
 use noble::Noble;
 use algo;
-use rand::{thread_rng, Rng};
+use algo::Evaluator;
+use eval::{Weights, DEFAULT_EVALUATOR};
+use rand::{thread_rng, Rng, SeedableRng, StdRng};
+use std::fmt;
 use std::io;
+use std::str::FromStr;
 use iter::CopyingIterator;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 pub const MINIMUM_TO_TAKE_2_TOKENS: u8 = 4;
 pub const NOBLE_SCORE: u8 = 3;
 pub const MAXIMUM_RESERVED: usize = 3;
 pub const MAXIMUM_COINS: u8 = 10;
 pub const SCORE_TO_WIN: u8 = 15;
+// Both players need at most this many points to win before the endgame solver is tried
+pub const ENDGAME_MARGIN: u8 = 5;
 
 #[derive(Debug)]
 #[derive(Clone)]
 #[derive(Copy)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Deck {
     One,
     Two,
@@ -30,6 +43,10 @@ type CardIndex = u8;
 #[derive(Clone)]
 #[derive(Copy)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum Move {
     Take { tokens: Tokens, drop: Tokens },
     Reserve { index: CardIndex, deck: Deck, drop: Tokens, joker: bool },
@@ -38,8 +55,145 @@ pub enum Move {
     Pass,
 }
 
+// Decks and cards are numbered from 1, with deck 1 the cheapest, so `buy 3.2` buys the second
+// card of the most expensive row
+impl fmt::Display for Deck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Deck::One => write!(f, "1"),
+            Deck::Two => write!(f, "2"),
+            Deck::Three => write!(f, "3"),
+        }
+    }
+}
+
+// The notation used by the UI, logs and files: `take KBG`, `take 2R drop W`, `reserve 1.4`,
+// `buy 3.2 noble 1`, `buyres 2` and `pass`. Numbers count from 1 and tokens are written like
+// `Tokens`. What a buy costs and whether a reservation gets a joker follow from the position, so
+// they're left out, see `State::parse_move`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn drop(f: &mut fmt::Formatter, drop: &Tokens) -> fmt::Result {
+            if drop.total() > 0 {
+                write!(f, " drop {}", drop)?;
+            }
+            Ok(())
+        }
+
+        fn noble(f: &mut fmt::Formatter, noble: Option<CardIndex>) -> fmt::Result {
+            if let Some(noble) = noble {
+                write!(f, " noble {}", noble + 1)?;
+            }
+            Ok(())
+        }
+
+        match *self {
+            Move::Take { ref tokens, drop: ref dropped } => {
+                write!(f, "take {}", tokens)?;
+                drop(f, dropped)
+            },
+            Move::Reserve { index, deck, drop: ref dropped, .. } => {
+                write!(f, "reserve {}.{}", deck, index + 1)?;
+                drop(f, dropped)
+            },
+            Move::Buy { index, deck, noble: n, .. } => {
+                write!(f, "buy {}.{}", deck, index + 1)?;
+                noble(f, n)
+            },
+            Move::BuyReserved { index, noble: n, .. } => {
+                write!(f, "buyres {}", index + 1)?;
+                noble(f, n)
+            },
+            Move::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+// Parses the notation written by `Display`. The cost of a buy is left empty and a reservation
+// doesn't get a joker, `State::parse_move` fills those in.
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Move, String> {
+        // Numbers in the notation count from 1
+        fn number(word: Option<&str>, max: u8) -> Result<CardIndex, String> {
+            let word = word.ok_or("expected a number")?;
+            match word.parse::<u8>() {
+                Ok(n) if n >= 1 && n <= max => Ok(n - 1),
+                _ => Err(format!("expected a number from 1 to {}, got {:?}", max, word)),
+            }
+        }
+
+        fn card(word: Option<&str>) -> Result<(Deck, CardIndex), String> {
+            let word = word.ok_or("expected a card like 1.4")?;
+            let mut parts = word.splitn(2, '.');
+            let deck = match number(parts.next(), 3)? {
+                0 => Deck::One,
+                1 => Deck::Two,
+                _ => Deck::Three,
+            };
+            Ok((deck, number(parts.next(), 4)?))
+        }
+
+        fn tokens(word: Option<&str>) -> Result<Tokens, String> {
+            word.ok_or_else(|| "expected tokens like KBG".to_string())?.parse()
+        }
+
+        let mut words = s.split_whitespace();
+        let verb = words.next().ok_or("empty move")?;
+        let mut mov = match verb {
+            "take" => Move::Take { tokens: tokens(words.next())?, drop: Tokens::empty() },
+            "reserve" => {
+                let (deck, index) = card(words.next())?;
+                Move::Reserve { index, deck, drop: Tokens::empty(), joker: false }
+            },
+            "buy" => {
+                let (deck, index) = card(words.next())?;
+                Move::Buy { index, deck, cost: Tokens::empty(), noble: None }
+            },
+            "buyres" => Move::BuyReserved { index: number(words.next(), MAXIMUM_RESERVED as u8)?, cost: Tokens::empty(), noble: None },
+            "pass" => Move::Pass,
+            _ => return Err(format!("unknown move {:?}, expected take, reserve, buy, buyres or pass", verb)),
+        };
+
+        while let Some(word) = words.next() {
+            match (word, &mut mov) {
+                ("drop", &mut Move::Take { ref mut drop, .. }) | ("drop", &mut Move::Reserve { ref mut drop, .. }) => *drop = tokens(words.next())?,
+                ("noble", &mut Move::Buy { ref mut noble, .. }) | ("noble", &mut Move::BuyReserved { ref mut noble, .. }) => *noble = Some(number(words.next(), 255)?),
+                _ => return Err(format!("unexpected {:?} in {:?}", word, s)),
+            }
+        }
+        Ok(mov)
+    }
+}
+
+impl Move {
+    // The move as it's written down, without what follows from the position
+    fn notation(&self) -> Move {
+        match *self {
+            Move::Reserve { index, deck, drop, .. } => Move::Reserve { index, deck, drop, joker: false },
+            Move::Buy { index, deck, noble, .. } => Move::Buy { index, deck, cost: Tokens::empty(), noble },
+            Move::BuyReserved { index, noble, .. } => Move::BuyReserved { index, cost: Tokens::empty(), noble },
+            mov => mov,
+        }
+    }
+
+    // The move without the choices that come with it, what is dropped and which noble visits
+    fn without_choices(&self) -> Move {
+        match self.notation() {
+            Move::Take { tokens, .. } => Move::Take { tokens, drop: Tokens::empty() },
+            Move::Reserve { index, deck, joker, .. } => Move::Reserve { index, deck, drop: Tokens::empty(), joker },
+            Move::Buy { index, deck, cost, .. } => Move::Buy { index, deck, cost, noble: None },
+            Move::BuyReserved { index, cost, .. } => Move::BuyReserved { index, cost, noble: None },
+            Move::Pass => Move::Pass,
+        }
+    }
+}
+
 #[derive(PartialEq)]
 #[derive(Debug)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct State {
     pub deck1: Vec<Card>,
     pub cards1: Vec<Card>,
@@ -64,30 +218,43 @@ pub type Score = i64;
 
 impl State {
     pub fn new(players: u8) -> State {
-        if players != 2 {
-            panic!("only 2 players")
-        }
-        let mut rng = thread_rng();
+        State::with_rng(players, &mut thread_rng())
+    }
+
+    // The same seed always deals the same game, see `record::Setup`
+    pub fn from_seed(players: u8, seed: u64) -> State {
+        let mut rng: StdRng = SeedableRng::from_seed(&[seed as usize][..]);
+        State::with_rng(players, &mut rng)
+    }
 
+    pub fn with_rng<R: Rng>(players: u8, rng: &mut R) -> State {
         let mut deck1 = Card::deck1();
         rng.shuffle(&mut deck1);
-        let new_deck1_len = deck1.len() - 4;
-        let cards1 = deck1.drain(new_deck1_len..).collect();
-
         let mut deck2 = Card::deck2();
         rng.shuffle(&mut deck2);
-        let new_deck2_len = deck2.len() - 4;
-        let cards2 = deck2.drain(new_deck2_len..).collect();
-
         let mut deck3 = Card::deck3();
         rng.shuffle(&mut deck3);
-        let new_deck3_len = deck3.len() - 4;
-        let cards3 = deck3.drain(new_deck3_len..).collect();
 
         let mut nobles = Noble::all();
         rng.shuffle(&mut nobles);
         nobles.truncate(3);
 
+        State::from_decks(players, deck1, deck2, deck3, nobles)
+    }
+
+    // Starts a game with decks in the given order, the last 4 cards of each are dealt to the market
+    pub fn from_decks(players: u8, mut deck1: Vec<Card>, mut deck2: Vec<Card>, mut deck3: Vec<Card>, nobles: Vec<Noble>) -> State {
+        if players != 2 {
+            panic!("only 2 players")
+        }
+
+        let new_deck1_len = deck1.len().saturating_sub(4);
+        let cards1 = deck1.drain(new_deck1_len..).collect();
+        let new_deck2_len = deck2.len().saturating_sub(4);
+        let cards2 = deck2.drain(new_deck2_len..).collect();
+        let new_deck3_len = deck3.len().saturating_sub(4);
+        let cards3 = deck3.drain(new_deck3_len..).collect();
+
         State {
             deck1: deck1,
             cards1: cards1,
@@ -104,203 +271,34 @@ impl State {
         }
     }
 
-    pub fn print(&self, out: &mut io::Write) -> io::Result<()> {
-        try!(write!(out, "Player: {}\n", self.adversary.score()));
-        fn print_cards(out: &mut io::Write, cards: &Vec<Card>) -> io::Result<()> {
-            if !cards.is_empty() {
-                for _ in cards.iter() {
-                    try!(write!(out, "┏━━━━━━━┓ "));
-                }
-                try!(write!(out, "\n"));
-                for card in cards.iter() {
-                    try!(write!(out, "┃{}     {}┃ ", card.color.code(), card.points));
-                }
-                try!(write!(out, "\n"));
-                for _ in cards.iter() {
-                    try!(write!(out, "┃       ┃ "));
-                }
-                try!(write!(out, "\n"));
-                for card in cards.iter() {
-                    try!(write!(out, "┃"));
-                    let mut count = 0;
-                    for color in Color::all_except_joker() {
-                        if card.cost[color] > 0 {
-                            try!(write!(out, "{}", color.code()));
-                            count += 1;
-                            if count < 4 {
-                                try!(write!(out, " "));
-                            }
-                        }
-                    }
-                    if count < 4 {
-                        try!(write!(out, " "));
-                        if count < 3 {
-                            try!(write!(out, "  "));
-                            if count < 2 {
-                                try!(write!(out, "  "));
-                            }
-                        }
-                    }
-                    try!(write!(out, "┃ "));
-                }
-                try!(write!(out, "\n"));
-                for card in cards.iter() {
-                    try!(write!(out, "┃"));
-                    let mut count = 0;
-                    for color in Color::all_except_joker() {
-                        if card.cost[color] > 0 {
-                            try!(write!(out, "{}", card.cost[color]));
-                            count += 1;
-                            if count < 4 {
-                                try!(write!(out, " "));
-                            }
-                        }
-                    }
-                    if count < 4 {
-                        try!(write!(out, " "));
-                        if count < 3 {
-                            try!(write!(out, "  "));
-                            if count < 2 {
-                                try!(write!(out, "  "));
-                            }
-                        }
-                    }
-                    try!(write!(out, "┃ "));
-                }
-                try!(write!(out, "\n"));
-                for _ in cards.iter() {
-                    try!(write!(out, "┗━━━━━━━┛ "));
-                }
-                try!(write!(out, "\n"));
-            }
-            Ok(())
-        }
-        fn print_player(out: &mut io::Write, player: &Player) -> io::Result<()> {
-            if !player.reserved.is_empty() {
-                try!(write!(out, "Reserved\n"));
-                try!(print_cards(out, &player.reserved));
-            }
-            for color in Color::all() {
-                try!(write!(out, "{}: {}", color.code(), player.tokens[color]));
-                let count = player.cards.iter().filter(|ref card| card.color == color).count();
-                if count > 0 {
-                    try!(write!(out, " + {}", count));
-                }
-                try!(write!(out, "\n"));
+    // Deal new cards from the decks to replace the ones that were bought or reserved
+    pub fn refill_market(&mut self) {
+        if self.cards1.len() < 4 {
+            if let Some(card) = self.deck1.pop() {
+                self.cards1.push(card);
             }
-            Ok(())
-        }
-        try!(print_player(out, &self.adversary));
-        try!(write!(out, "\n"));
-
-        try!(write!(out, "Adversary: {}\n", self.player.score()));
-        try!(print_player(out, &self.player));
-
-        try!(write!(out, "\nBank\n"));
-        for color in Color::all() {
-            try!(write!(out, "{} ", color.code()));
-        }
-        try!(write!(out, "\n"));
-        for color in Color::all() {
-            try!(write!(out, "{} ", self.bank[color]));
         }
-        try!(write!(out, "\n\n"));
-
-        if !self.nobles.is_empty() {
-            try!(write!(out, "\nNobles\n"));
-            for _ in self.nobles.iter() {
-                try!(write!(out, "┏━━━━━┓ "));
-            }
-            try!(write!(out, "\n"));
-            for noble in self.nobles.iter() {
-                try!(write!(out, "┃"));
-                let mut count = 0;
-                for color in Color::all_except_joker() {
-                    if noble.cost[color] > 0 {
-                        try!(write!(out, "{}", color.code()));
-                        count += 1;
-                        if count < 3 {
-                            try!(write!(out, " "));
-                        }
-                    }
-                }
-                if count < 3 {
-                    try!(write!(out, " "));
-                }
-                try!(write!(out, "┃ "));
-            }
-            try!(write!(out, "\n"));
-            for noble in self.nobles.iter() {
-                try!(write!(out, "┃"));
-                let mut count = 0;
-                for color in Color::all_except_joker() {
-                    if noble.cost[color] > 0 {
-                        try!(write!(out, "{}", noble.cost[color]));
-                        count += 1;
-                        if count < 3 {
-                            try!(write!(out, " "));
-                        }
-                    }
-                }
-                if count < 3 {
-                    try!(write!(out, " "));
-                }
-                try!(write!(out, "┃ "));
-            }
-            try!(write!(out, "\n"));
-            for _ in self.nobles.iter() {
-                try!(write!(out, "┗━━━━━┛ "));
+        if self.cards2.len() < 4 {
+            if let Some(card) = self.deck2.pop() {
+                self.cards2.push(card);
             }
-            try!(write!(out, "\n"));
         }
-        try!(write!(out, "\n"));
-        try!(print_cards(out, &self.cards3));
-        try!(print_cards(out, &self.cards2));
-        try!(print_cards(out, &self.cards1));
-        Ok(())
-    }
-}
-
-impl algo::State for State {
-    type Score = Score;
-    type Move = Move;
-
-    fn score(&self) -> Score {
-        let card_multiplier = self.nobles.iter().fold(Tokens::empty(), |acc, noble| acc.max(&noble.cost));
-        let player_score = self.player.score();
-        let adversary_score = self.adversary.score();
-        let mut score = (player_score as Score - adversary_score as Score) * 3000;
-
-        if player_score >= SCORE_TO_WIN {
-            if player_score < adversary_score {
-                score -= 1000000;
-            } else {
-                score += 1000000;
+        if self.cards3.len() < 4 {
+            if let Some(card) = self.deck3.pop() {
+                self.cards3.push(card);
             }
-        } else if adversary_score >= SCORE_TO_WIN {
-            score -= 1000000;
         }
-
-        score += self.player.card_score(&card_multiplier);
-        score -= self.adversary.card_score(&card_multiplier);
-
-        score += self.player.token_score();
-        score -= self.adversary.token_score();
-
-        score -= self.player.reserved.len() as Score * 20;
-        score += self.adversary.reserved.len() as Score * 20;
-
-        score
     }
 
-    fn generate_moves(&self) -> Vec<Move> {
-        let mut moves = Vec::new();
-        let player: &Player = if self.players_turn {
+    fn player_to_move(&self) -> &Player {
+        if self.players_turn {
             &self.player
         } else {
             &self.adversary
-        };
+        }
+    }
 
+    fn push_buys(&self, player: &Player, moves: &mut Vec<Move>) {
         let mut tokens_from_cards = player.tokens_from_cards();
 
         fn push_card_with_nobles<F>(tokens_from_cards: &mut Tokens, nobles: &Vec<Noble>, moves: &mut Vec<Move>, color: Color, f: F) where F: Fn(Option<u8>) -> Move {
@@ -322,7 +320,7 @@ impl algo::State for State {
         // Do most benificial moves first to get benefits of α β pruning
         for (i, card) in self.cards3.iter().enumerate() {
             if let Some(cost) = player.cost_for(card) {
-                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, &mut moves, card.color, |noble: Option<u8>| {
+                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, moves, card.color, |noble: Option<u8>| {
                     Move::Buy {
                         index: i as u8,
                         deck: Deck::Three,
@@ -335,7 +333,7 @@ impl algo::State for State {
 
         for (i, card) in self.cards2.iter().enumerate() {
             if let Some(cost) = player.cost_for(card) {
-                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, &mut moves, card.color, |noble: Option<u8>| {
+                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, moves, card.color, |noble: Option<u8>| {
                     Move::Buy {
                         index: i as u8,
                         deck: Deck::Two,
@@ -348,7 +346,7 @@ impl algo::State for State {
 
         for (i, card) in self.cards1.iter().enumerate() {
             if let Some(cost) = player.cost_for(card) {
-                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, &mut moves, card.color, |noble: Option<u8>| {
+                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, moves, card.color, |noble: Option<u8>| {
                     Move::Buy {
                         index: i as u8,
                         deck: Deck::One,
@@ -361,7 +359,7 @@ impl algo::State for State {
 
         for (i, card) in player.reserved.iter().enumerate() {
             if let Some(cost) = player.cost_for(card) {
-                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, &mut moves, card.color, |noble: Option<u8>| {
+                push_card_with_nobles(&mut tokens_from_cards, &self.nobles, moves, card.color, |noble: Option<u8>| {
                     Move::BuyReserved {
                         index: i as u8,
                         cost: cost,
@@ -370,9 +368,24 @@ impl algo::State for State {
                 });
             }
         }
+    }
 
-        // The index is how many tokens need to be discarded
-        let discard_options = player.tokens.discard_permutations();
+    // Buying a card that wins the game or gets a noble. The move doesn't have to be legal.
+    fn is_decisive(&self, player: &Player, mov: &Move) -> bool {
+        let card = match *mov {
+            Move::Buy { noble: Some(_), .. } | Move::BuyReserved { noble: Some(_), .. } => return true,
+            Move::Buy { index, deck: Deck::One, .. } => self.cards1.get(index as usize),
+            Move::Buy { index, deck: Deck::Two, .. } => self.cards2.get(index as usize),
+            Move::Buy { index, deck: Deck::Three, .. } => self.cards3.get(index as usize),
+            Move::BuyReserved { index, .. } => player.reserved.get(index as usize),
+            _ => return false,
+        };
+        card.is_some_and(|card| player.score() + card.points >= SCORE_TO_WIN)
+    }
+
+    // `discard_options` are the player's `discard_permutations`, the index is how many tokens need
+    // to be discarded
+    fn push_takes(&self, player: &Player, discard_options: &[Vec<Tokens>; 4], moves: &mut Vec<Move>) {
         let total = player.tokens.total();
 
         for (color1, iter2) in Color::all_except_joker().copying() {
@@ -452,7 +465,10 @@ impl algo::State for State {
                 }
             }
         }
+    }
 
+    fn push_reserves(&self, player: &Player, discard_options: &[Vec<Tokens>; 4], moves: &mut Vec<Move>) {
+        let total = player.tokens.total();
         if player.reserved.len() < MAXIMUM_RESERVED {
             // Can I get a joker?
             let joker = self.bank.joker > 0;
@@ -497,6 +513,266 @@ impl algo::State for State {
                 }
             }
         }
+    }
+
+    // How many tokens of each color the player could spend on the most expensive card they can see
+    // or have reserved, after what their cards pay for. Jokers can be spent on anything.
+    fn useful_tokens(&self, player: &Player) -> Tokens {
+        let tokens_from_cards = player.tokens_from_cards();
+        let mut useful = Tokens::empty();
+        for card in self.cards1.iter().chain(self.cards2.iter()).chain(self.cards3.iter()).chain(player.reserved.iter()) {
+            for color in Color::all_except_joker() {
+                useful[color] = cmp::max(useful[color], card.cost[color].saturating_sub(tokens_from_cards[color]));
+            }
+        }
+        useful.joker = MAXIMUM_COINS;
+        useful
+    }
+
+    // A move that drops tokens is dominated when another move that only differs in what it drops
+    // leaves the player with at least as many useful tokens of every color. Only the first of the
+    // moves that leave the same useful tokens is kept.
+    fn prune_dominated(&self, player: &Player, moves: &mut Vec<Move>) {
+        let useful = self.useful_tokens(player);
+        // The move with the drop left out, and the useful tokens the player ends up with
+        let kept = |mov: &Move| -> Option<(Move, Tokens)> {
+            let (rest, mut tokens) = match *mov {
+                Move::Take { tokens, drop } if drop.total() > 0 => (Move::Take { tokens, drop: Tokens::empty() }, player.tokens + tokens - drop),
+                Move::Reserve { index, deck, drop, joker } if drop.total() > 0 => {
+                    let mut tokens = player.tokens - drop;
+                    tokens.joker += 1;
+                    (Move::Reserve { index, deck, drop: Tokens::empty(), joker }, tokens)
+                },
+                _ => return None,
+            };
+            for color in Color::all() {
+                tokens[color] = cmp::min(tokens[color], useful[color]);
+            }
+            Some((rest, tokens))
+        };
+        let at_least = |a: &Tokens, b: &Tokens| Color::all().all(|color| a[color] >= b[color]);
+
+        let candidates: Vec<Option<(Move, Tokens)>> = moves.iter().map(&kept).collect();
+        let mut i = 0;
+        moves.retain(|_| {
+            let index = i;
+            i += 1;
+            let (rest, tokens) = match candidates[index] {
+                Some(candidate) => candidate,
+                None => return true,
+            };
+            !candidates.iter().enumerate().any(|(other, candidate)| match *candidate {
+                Some((other_rest, ref other_tokens)) => other != index && other_rest == rest && at_least(other_tokens, &tokens) &&
+                    (other < index || !at_least(&tokens, other_tokens)),
+                None => false,
+            })
+        });
+    }
+
+    // Parses a move in the notation of `Move`'s `Display` and finds it among the legal moves, which
+    // know what it costs. If it isn't legal the error lists the legal moves that only make
+    // different choices, like what to drop.
+    pub fn parse_move(&self, s: &str) -> Result<Move, String> {
+        let notation = s.parse::<Move>()?;
+        let moves = algo::State::generate_moves(self);
+        if let Some(mov) = moves.iter().find(|mov| mov.notation() == notation) {
+            return Ok(*mov);
+        }
+        let alternatives: Vec<String> = moves.iter()
+            .filter(|mov| mov.without_choices().notation() == notation.without_choices())
+            .map(|mov| mov.to_string())
+            .collect();
+        if alternatives.is_empty() {
+            Err(format!("{} isn't legal", notation))
+        } else {
+            Err(format!("{} isn't legal, try {}", notation, alternatives.join(" or ")))
+        }
+    }
+
+    pub fn print(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(out, "Player: {}", self.adversary.score())?;
+        fn print_cards(out: &mut dyn io::Write, cards: &[Card]) -> io::Result<()> {
+            if !cards.is_empty() {
+                for _ in cards.iter() {
+                    write!(out, "┏━━━━━━━┓ ")?;
+                }
+                writeln!(out)?;
+                for card in cards.iter() {
+                    write!(out, "┃{}     {}┃ ", card.color.code(), card.points)?;
+                }
+                writeln!(out)?;
+                for _ in cards.iter() {
+                    write!(out, "┃       ┃ ")?;
+                }
+                writeln!(out)?;
+                for card in cards.iter() {
+                    write!(out, "┃")?;
+                    let mut count = 0;
+                    for color in Color::all_except_joker() {
+                        if card.cost[color] > 0 {
+                            write!(out, "{}", color.code())?;
+                            count += 1;
+                            if count < 4 {
+                                write!(out, " ")?;
+                            }
+                        }
+                    }
+                    if count < 4 {
+                        write!(out, " ")?;
+                        if count < 3 {
+                            write!(out, "  ")?;
+                            if count < 2 {
+                                write!(out, "  ")?;
+                            }
+                        }
+                    }
+                    write!(out, "┃ ")?;
+                }
+                writeln!(out)?;
+                for card in cards.iter() {
+                    write!(out, "┃")?;
+                    let mut count = 0;
+                    for color in Color::all_except_joker() {
+                        if card.cost[color] > 0 {
+                            write!(out, "{}", card.cost[color])?;
+                            count += 1;
+                            if count < 4 {
+                                write!(out, " ")?;
+                            }
+                        }
+                    }
+                    if count < 4 {
+                        write!(out, " ")?;
+                        if count < 3 {
+                            write!(out, "  ")?;
+                            if count < 2 {
+                                write!(out, "  ")?;
+                            }
+                        }
+                    }
+                    write!(out, "┃ ")?;
+                }
+                writeln!(out)?;
+                for _ in cards.iter() {
+                    write!(out, "┗━━━━━━━┛ ")?;
+                }
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        fn print_player(out: &mut dyn io::Write, player: &Player) -> io::Result<()> {
+            if !player.reserved.is_empty() {
+                writeln!(out, "Reserved")?;
+                print_cards(out, &player.reserved)?;
+            }
+            for color in Color::all() {
+                write!(out, "{}: {}", color.code(), player.tokens[color])?;
+                let count = player.cards.iter().filter(|ref card| card.color == color).count();
+                if count > 0 {
+                    write!(out, " + {}", count)?;
+                }
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        print_player(out, &self.adversary)?;
+        writeln!(out)?;
+
+        writeln!(out, "Adversary: {}", self.player.score())?;
+        print_player(out, &self.player)?;
+
+        writeln!(out, "\nBank")?;
+        for color in Color::all() {
+            write!(out, "{} ", color.code())?;
+        }
+        writeln!(out)?;
+        for color in Color::all() {
+            write!(out, "{} ", self.bank[color])?;
+        }
+        writeln!(out, "\n")?;
+
+        if !self.nobles.is_empty() {
+            writeln!(out, "\nNobles")?;
+            for _ in self.nobles.iter() {
+                write!(out, "┏━━━━━┓ ")?;
+            }
+            writeln!(out)?;
+            for noble in self.nobles.iter() {
+                write!(out, "┃")?;
+                let mut count = 0;
+                for color in Color::all_except_joker() {
+                    if noble.cost[color] > 0 {
+                        write!(out, "{}", color.code())?;
+                        count += 1;
+                        if count < 3 {
+                            write!(out, " ")?;
+                        }
+                    }
+                }
+                if count < 3 {
+                    write!(out, " ")?;
+                }
+                write!(out, "┃ ")?;
+            }
+            writeln!(out)?;
+            for noble in self.nobles.iter() {
+                write!(out, "┃")?;
+                let mut count = 0;
+                for color in Color::all_except_joker() {
+                    if noble.cost[color] > 0 {
+                        write!(out, "{}", noble.cost[color])?;
+                        count += 1;
+                        if count < 3 {
+                            write!(out, " ")?;
+                        }
+                    }
+                }
+                if count < 3 {
+                    write!(out, " ")?;
+                }
+                write!(out, "┃ ")?;
+            }
+            writeln!(out)?;
+            for _ in self.nobles.iter() {
+                write!(out, "┗━━━━━┛ ")?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out)?;
+        print_cards(out, &self.cards3)?;
+        print_cards(out, &self.cards2)?;
+        print_cards(out, &self.cards1)?;
+        Ok(())
+    }
+}
+
+// What generating the stages of a position found so far, see `generate_stage`
+#[derive(Default)]
+pub struct Stages {
+    // The buys of whichever of the first two stages hasn't been handed out yet, both come out of
+    // generating the buys once
+    buys: Option<Vec<Move>>,
+    // How many moves each of the stages before reserving held, once generated
+    counts: [Option<usize>; 3],
+}
+
+impl algo::State for State {
+    type Score = Score;
+    type Move = Move;
+    type Stages = Stages;
+
+    fn score(&self) -> Score {
+        DEFAULT_EVALUATOR.evaluate(self)
+    }
+
+    fn generate_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let player = self.player_to_move();
+        let discard_options = player.tokens.discard_permutations();
+
+        self.push_buys(player, &mut moves);
+        self.push_takes(player, &discard_options, &mut moves);
+        self.push_reserves(player, &discard_options, &mut moves);
 
         if moves.len() == 0 {
             moves.push(Move::Pass);
@@ -505,6 +781,68 @@ impl algo::State for State {
         moves
     }
 
+    // Buys that win the game or get a noble, the other buys, takes and then reserves. Passing is
+    // only possible when there are no other moves, which can only be the case when no more cards
+    // can be reserved.
+    fn generate_stage(&self, stage: usize, stages: &mut Stages) -> Option<Vec<Move>> {
+        let player = self.player_to_move();
+        let moves = match stage {
+            0 | 1 => match stages.buys.take() {
+                Some(buys) => buys,
+                None => {
+                    let mut buys = Vec::new();
+                    self.push_buys(player, &mut buys);
+                    let (decisive, others): (Vec<Move>, Vec<Move>) = buys.into_iter().partition(|mov| self.is_decisive(player, mov));
+                    let (moves, rest) = if stage == 0 { (decisive, others) } else { (others, decisive) };
+                    stages.buys = Some(rest);
+                    moves
+                },
+            },
+            2 => {
+                let mut moves = Vec::new();
+                self.push_takes(player, &player.tokens.discard_permutations(), &mut moves);
+                moves
+            },
+            3 => {
+                let mut moves = Vec::new();
+                self.push_reserves(player, &player.tokens.discard_permutations(), &mut moves);
+                if moves.is_empty() {
+                    // The stages that weren't asked for yet are only generated to count them
+                    let mut others = 0;
+                    for earlier in 0..3 {
+                        others += match stages.counts[earlier] {
+                            Some(count) => count,
+                            None => self.generate_stage(earlier, stages).map_or(0, |moves| moves.len()),
+                        };
+                    }
+                    if others == 0 {
+                        moves.push(Move::Pass);
+                    }
+                }
+                moves
+            },
+            _ => return None,
+        };
+        if stage < 3 {
+            stages.counts[stage] = Some(moves.len());
+        }
+        Some(moves)
+    }
+
+    fn stage_of(&self, mov: &Move) -> usize {
+        match *mov {
+            Move::Buy { .. } | Move::BuyReserved { .. } => if self.is_decisive(self.player_to_move(), mov) { 0 } else { 1 },
+            Move::Take { .. } => 2,
+            Move::Reserve { .. } | Move::Pass => 3,
+        }
+    }
+
+    // Leaves out moves that only differ from another one in which tokens they drop, when the other
+    // keeps at least as many useful tokens
+    fn prune(&self, moves: &mut Vec<Move>) {
+        self.prune_dominated(self.player_to_move(), moves);
+    }
+
     fn apply(&mut self, mov: &Move) {
         match *mov {
             Move::Take { tokens, drop } => {
@@ -681,8 +1019,93 @@ impl algo::State for State {
     }
 }
 
+// A move with the card it buys or reserves in place of where that card lies, which differs between
+// determinizations once new cards have been dealt, see `Determinize::key`
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct MoveKey {
+    // With the index of the card left out
+    mov: Move,
+    card: Option<Card>,
+}
+
+impl algo::Determinize for State {
+    type Key = MoveKey;
+
+    // The order of the decks is the only thing neither player can see
+    fn determinize<R: Rng>(&self, rng: &mut R) -> State {
+        let mut state = self.clone();
+        rng.shuffle(&mut state.deck1);
+        rng.shuffle(&mut state.deck2);
+        rng.shuffle(&mut state.deck3);
+        state
+    }
+
+    fn key(&self, mov: &Move) -> MoveKey {
+        let market = |deck| match deck {
+            Deck::One => &self.cards1,
+            Deck::Two => &self.cards2,
+            Deck::Three => &self.cards3,
+        };
+        let player = if self.players_turn { &self.player } else { &self.adversary };
+        let (mov, card) = match *mov {
+            Move::Reserve { index, deck, drop, joker } => (Move::Reserve { index: 0, deck, drop, joker }, market(deck).get(index as usize)),
+            Move::Buy { index, deck, cost, noble } => (Move::Buy { index: 0, deck, cost, noble }, market(deck).get(index as usize)),
+            Move::BuyReserved { index, cost, noble } => (Move::BuyReserved { index: 0, cost, noble }, player.reserved.get(index as usize)),
+            mov => (mov, None),
+        };
+        MoveKey {
+            mov,
+            card: card.cloned(),
+        }
+    }
+
+    fn deal(&mut self) {
+        self.refill_market();
+    }
+}
+
+impl algo::MultiplayerState for State {
+    fn players(&self) -> usize {
+        2
+    }
+
+    fn current_player(&self) -> usize {
+        if self.players_turn { 0 } else { 1 }
+    }
+
+    fn score_for(&self, player: usize) -> Score {
+        let score = algo::State::score(self);
+        if player == 0 { score } else { -score }
+    }
+}
+
+impl algo::Endgame for State {
+    // Ties are won by the player with the fewest cards
+    fn outcome(&self) -> Option<algo::Outcome> {
+        if !algo::State::is_terminal(self) {
+            return None;
+        }
+
+        let player = (self.player.score(), Reverse(self.player.cards.len()));
+        let adversary = (self.adversary.score(), Reverse(self.adversary.cards.len()));
+        Some(match player.cmp(&adversary) {
+            Ordering::Greater => algo::Outcome::Win,
+            Ordering::Less => algo::Outcome::Loss,
+            Ordering::Equal => algo::Outcome::Draw,
+        })
+    }
+
+    fn is_endgame(&self) -> bool {
+        self.player.score() + ENDGAME_MARGIN >= SCORE_TO_WIN && self.adversary.score() + ENDGAME_MARGIN >= SCORE_TO_WIN
+    }
+}
+
 #[derive(PartialEq)]
 #[derive(Debug)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Player {
     pub tokens: Tokens,
     pub cards: Vec<Card>,
@@ -708,7 +1131,7 @@ impl Player {
         tokens
     }
 
-    pub fn card_score(&self, noble_card_bonus: &Tokens) -> Score {
+    pub fn card_score(&self, noble_card_bonus: &Tokens, weights: &Weights) -> Score {
         let mut points = 0;
         // Give a bonus to multiple of the same card
         let mut multiple_card_bonus = Tokens {
@@ -722,7 +1145,7 @@ impl Player {
 
         for card in self.cards.iter() {
             let multi_bonus = multiple_card_bonus[card.color] as Score;
-            points += (noble_card_bonus[card.color] as Score) * 100 + multi_bonus * 100 + 250;
+            points += (noble_card_bonus[card.color] as Score) * weights.noble_color + multi_bonus * weights.same_color + weights.card;
             if multiple_card_bonus[card.color] < 5 {
                 multiple_card_bonus[card.color] += 1;
             }
@@ -731,9 +1154,8 @@ impl Player {
         points
     }
 
-    pub fn token_score(&self) -> Score {
-        // Give jokers 50% more value than other tokens
-        ((min(MAXIMUM_COINS, self.tokens.total()) * 2 + self.tokens.joker) as Score) * 4
+    pub fn token_score(&self, weights: &Weights) -> Score {
+        (min(MAXIMUM_COINS, self.tokens.total()) as Score) * weights.token + (self.tokens.joker as Score) * weights.joker
     }
 
     pub fn score(&self) -> u8 {
@@ -781,6 +1203,415 @@ mod tests {
         assert!(false);
     }
 
+    #[test]
+    fn determinize_only_shuffles_decks() {
+        let state = super::State::new(2);
+        let determinization = algo::Determinize::determinize(&state, &mut thread_rng());
+        assert_eq!(determinization.cards1, state.cards1);
+        assert_eq!(determinization.cards2, state.cards2);
+        assert_eq!(determinization.cards3, state.cards3);
+        assert_eq!(determinization.nobles, state.nobles);
+        assert_eq!(determinization.bank, state.bank);
+
+        for &(sampled, actual) in [(&determinization.deck1, &state.deck1),
+                                  (&determinization.deck2, &state.deck2),
+                                  (&determinization.deck3, &state.deck3)].iter() {
+            assert_eq!(sampled.len(), actual.len());
+            assert!(sampled.iter().all(|card| actual.contains(card)));
+        }
+    }
+
+    #[test]
+    fn ismcts_picks_legal_moves() {
+        let state = super::State::new(2);
+        let config = algo::SearchConfig {
+            node_limit: Some(2000),
+            ..algo::SearchConfig::default()
+        };
+        let result = algo::ismcts(&state, &algo::ScoreEvaluator, &config, &mut thread_rng());
+        let possible_moves = state.generate_moves();
+        assert!(!result.moves.is_empty());
+        assert!(result.moves.iter().all(|mov| possible_moves.contains(mov)));
+        assert!(result.nodes >= 2000 && result.nodes < 2000 + 200);
+    }
+
+    #[test]
+    fn keys_follow_the_cards() {
+        use algo::Determinize;
+        let mut state = super::State::new(2);
+        let buy = Move::Buy { index: 1, deck: Deck::One, cost: Tokens::empty(), noble: None };
+        let key = state.key(&buy);
+        let card = state.cards1.remove(0);
+        state.cards1.push(card);
+        assert_eq!(state.key(&Move::Buy { index: 0, deck: Deck::One, cost: Tokens::empty(), noble: None }), key);
+        assert!(state.key(&buy) != key);
+    }
+
+    #[test]
+    fn parallel_alphabeta_finds_the_best_moves() {
+        let config = algo::SearchConfig {
+            tie_break: algo::TieBreak::First,
+            ..algo::SearchConfig::default()
+        };
+        let mut state = super::State::new(2);
+        let parallel_result = algo::parallel_alphabeta(&state, &algo::ScoreEvaluator, &config);
+        let result = algo::alphabeta(&mut state, &algo::ScoreEvaluator, &config);
+        assert_eq!(parallel_result.score, result.score);
+
+        // The same moves, though the principal variations may start with different ones
+        assert_eq!(parallel_result.moves.len(), result.moves.len());
+        assert!(parallel_result.moves.iter().all(|mov| result.moves.contains(mov)));
+        let mut rng = thread_rng();
+        assert_eq!(config.tie_break.pick(&parallel_result.moves, &mut rng), parallel_result.pv.first());
+        assert_eq!(config.tie_break.pick(&result.moves, &mut rng), result.pv.first());
+    }
+
+    #[test]
+    fn alphabeta_principal_variation() {
+        let mut state = super::State::new(2);
+        let result = algo::alphabeta(&mut state, &algo::ScoreEvaluator, &algo::SearchConfig::default());
+        assert_eq!(result.pv.first(), result.moves.first());
+        assert!(result.pv.len() <= result.depth);
+        assert!(result.nodes > 0);
+
+        // Playing out the principal variation should reach the position that was scored
+        for mov in result.pv.iter() {
+            state.apply(mov);
+        }
+        if result.pv.len() == result.depth {
+            assert_eq!(state.score(), result.score);
+        }
+    }
+
+    #[test]
+    fn search_respects_node_limit() {
+        let mut state = super::State::new(2);
+        for algorithm in [algo::Algorithm::Minimax, algo::Algorithm::AlphaBeta].iter() {
+            let config = algo::SearchConfig {
+                algorithm: *algorithm,
+                node_limit: Some(1000),
+                ..algo::SearchConfig::default()
+            };
+            let result = algo::search(&mut state, &algo::ScoreEvaluator, &config);
+            assert!(!result.moves.is_empty());
+            assert!(result.nodes <= 1000 + result.depth as u64);
+        }
+
+        let config = algo::SearchConfig {
+            depth: 2,
+            ..algo::SearchConfig::default()
+        };
+        assert_eq!(algo::alphabeta(&mut state, &algo::ScoreEvaluator, &config).depth, 2);
+    }
+
+    #[test]
+    fn stopped_search_returns_best_so_far() {
+        use std::thread;
+        use std::time::Duration;
+
+        let mut state = super::State::new(2);
+        for algorithm in [algo::Algorithm::Minimax, algo::Algorithm::AlphaBeta, algo::Algorithm::Parallel].iter() {
+            // Far too deep to finish before it's stopped
+            let config = algo::SearchConfig {
+                algorithm: *algorithm,
+                depth: 12,
+                ..algo::SearchConfig::default()
+            };
+            let stop = config.stop.clone();
+            let stopper = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                stop.stop();
+            });
+            let result = algo::search(&mut state, &algo::ScoreEvaluator, &config);
+            stopper.join().unwrap();
+            assert!(!result.moves.is_empty());
+            assert!(result.elapsed < Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn prune_dominated_drops() {
+        let mut state = super::State::new(2);
+        // Nothing on the board costs white, so white tokens are the ones to drop
+        let card = Card {
+            color: Color::Blue,
+            cost: Tokens { black: 3, blue: 3, green: 3, red: 4, white: 0, joker: 0 },
+            points: 1,
+        };
+        for cards in [&mut state.cards1, &mut state.cards2, &mut state.cards3].iter_mut() {
+            for slot in cards.iter_mut() {
+                *slot = card.clone();
+            }
+        }
+        state.player.tokens = Tokens { black: 2, blue: 2, green: 1, red: 2, white: 3, joker: 0 };
+        let take = Tokens { black: 1, blue: 1, green: 1, red: 0, white: 0, joker: 0 };
+        let drops = |state: &super::State, prune: bool| -> Vec<Tokens> {
+            let mut moves = state.generate_moves();
+            if prune {
+                state.prune(&mut moves);
+            }
+            moves.into_iter().filter_map(|mov| match mov {
+                Move::Take { tokens, drop } if tokens == take => Some(drop),
+                _ => None,
+            }).collect()
+        };
+
+        assert_eq!(drops(&state, false).len(), 3);
+        assert_eq!(drops(&state, true), vec![Tokens { black: 0, blue: 0, green: 0, red: 0, white: 3, joker: 0 }]);
+    }
+
+    #[test]
+    fn pruning_keeps_a_best_move() {
+        use rand::{thread_rng, Rng};
+
+        // Deep enough for the player to spend the tokens they kept
+        let config = algo::SearchConfig {
+            depth: 3,
+            ..algo::SearchConfig::default()
+        };
+        let mut rng = thread_rng();
+        let mut positions = 0;
+        while positions < 20 {
+            // Play randomly until someone has to drop tokens, giving up on games where random
+            // players end up passing forever
+            let mut state = super::State::new(2);
+            let mut plies = 0;
+            while !state.is_terminal() && plies < 200 {
+                let player = if state.players_turn { &state.player } else { &state.adversary };
+                if player.tokens.total() >= 9 {
+                    break;
+                }
+                let mov = *rng.choose(&state.generate_moves()).unwrap();
+                state.apply(&mov);
+                state.refill_market();
+                plies += 1;
+            }
+            if state.is_terminal() || plies == 200 {
+                continue;
+            }
+            positions += 1;
+
+            let evaluator = algo::ScoreEvaluator;
+            let best = if state.players_turn {
+                algo::alphabeta(&mut state, &evaluator, &config).moves
+            } else {
+                algo::alphabeta(&mut state, &algo::Negated(evaluator), &config).moves
+            };
+            let all_moves = state.generate_moves();
+            let mut moves = all_moves.clone();
+            state.prune(&mut moves);
+            assert!(moves.iter().all(|mov| all_moves.contains(mov)));
+            assert!(moves.iter().any(|mov| best.contains(mov)), "{:?} pruned from {:?}", best, moves);
+
+            let pruned_config = algo::SearchConfig {
+                prune: true,
+                ..config.clone()
+            };
+            let pruned = if state.players_turn {
+                algo::alphabeta(&mut state, &evaluator, &pruned_config).moves
+            } else {
+                algo::alphabeta(&mut state, &algo::Negated(evaluator), &pruned_config).moves
+            };
+            assert!(pruned.iter().all(|mov| moves.contains(mov)));
+        }
+    }
+
+    #[test]
+    fn move_notation() {
+        let mut tokens = Tokens::one(Color::Black);
+        tokens.blue = 1;
+        tokens.green = 1;
+        let take = Move::Take { tokens, drop: Tokens::empty() };
+        assert_eq!(take.to_string(), "take KBG");
+        assert_eq!("take KBG".parse(), Ok(take));
+        let take = Move::Take { tokens: "2R".parse().unwrap(), drop: Tokens::one(Color::White) };
+        assert_eq!(take.to_string(), "take 2R drop W");
+        assert_eq!("take 2r  drop w".parse(), Ok(take));
+        let buy = Move::Buy { index: 1, deck: Deck::Three, cost: Tokens::one(Color::Red), noble: Some(0) };
+        assert_eq!(buy.to_string(), "buy 3.2 noble 1");
+        let reserve = Move::Reserve { index: 3, deck: Deck::One, drop: Tokens::empty(), joker: true };
+        assert_eq!(reserve.to_string(), "reserve 1.4");
+        assert_eq!(Move::BuyReserved { index: 1, cost: Tokens::empty(), noble: None }.to_string(), "buyres 2");
+        assert_eq!("pass".parse(), Ok(Move::Pass));
+
+        for bad in ["", "take", "take KX", "buy 4.1", "buy 1.5", "buy 1", "reserve 1.1 noble 1", "buyres 4", "pass now"].iter() {
+            assert!(bad.parse::<Move>().is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_shape() {
+        use serde_json;
+
+        let take = Move::Take { tokens: "2R".parse().unwrap(), drop: Tokens::one(Color::White) };
+        assert_eq!(serde_json::to_string(&take).unwrap(),
+                   r#"{"type":"take","tokens":{"black":0,"blue":0,"green":0,"red":2,"white":0,"joker":0},"drop":{"black":0,"blue":0,"green":0,"red":0,"white":1,"joker":0}}"#);
+        let buy = Move::BuyReserved { index: 1, cost: Tokens::empty(), noble: Some(0) };
+        assert!(serde_json::to_string(&buy).unwrap().starts_with(r#"{"type":"buy_reserved","index":1,"cost":{"#));
+        let reserve = Move::Reserve { index: 3, deck: Deck::One, drop: Tokens::empty(), joker: true };
+        assert!(serde_json::to_string(&reserve).unwrap().starts_with(r#"{"type":"reserve","index":3,"deck":"one","drop":{"#));
+        assert_eq!(serde_json::to_string(&Move::Pass).unwrap(), r#"{"type":"pass"}"#);
+        let card: Card = "K1:4B".parse().unwrap();
+        assert_eq!(serde_json::to_string(&card).unwrap(),
+                   r#"{"color":"black","cost":{"black":0,"blue":4,"green":0,"red":0,"white":0,"joker":0},"points":1}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        use rand::{thread_rng, Rng};
+        use serde_json;
+
+        let mut rng = thread_rng();
+        let mut state = super::State::new(2);
+        let mut plies = 0;
+        while !state.is_terminal() && plies < 200 {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(serde_json::from_str::<super::State>(&json).unwrap(), state);
+            let moves = state.generate_moves();
+            for mov in moves.iter() {
+                let json = serde_json::to_string(mov).unwrap();
+                assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), *mov);
+            }
+            let mov = *rng.choose(&moves).unwrap();
+            state.apply(&mov);
+            state.refill_market();
+            plies += 1;
+        }
+    }
+
+    #[test]
+    fn parse_every_legal_move() {
+        use rand::{thread_rng, Rng};
+
+        let mut rng = thread_rng();
+        let mut state = super::State::new(2);
+        let mut plies = 0;
+        // Random players can end up passing forever
+        while !state.is_terminal() && plies < 200 {
+            let moves = state.generate_moves();
+            for mov in moves.iter() {
+                assert_eq!(state.parse_move(&mov.to_string()), Ok(*mov));
+            }
+            let mov = *rng.choose(&moves).unwrap();
+            state.apply(&mov);
+            state.refill_market();
+            plies += 1;
+        }
+    }
+
+    #[test]
+    fn parse_illegal_move() {
+        let state = super::State::new(2);
+        assert!(state.parse_move("buyres 1").is_err());
+        assert!(state.parse_move("take KBG drop R").is_err());
+        assert!(state.parse_move("take KBG").is_ok());
+    }
+
+    #[test]
+    fn pass_only_without_other_moves() {
+        use std::collections::HashSet;
+
+        // With three cards reserved and none affordable only taking tokens is left, if there are any
+        for &(bank, pass) in [("-", true), ("1K1B1G", false)].iter() {
+            let position = format!("K1:4B - - - - - - {} -/-/K1:4B,K1:4B,K1:4B/- -/-/-/- p", bank);
+            let state = super::State::from_position(&position).unwrap();
+            assert_eq!(state.generate_moves() == vec![Move::Pass], pass);
+
+            // In any order, like when a killer move is tried first
+            for order in [[0, 1, 2, 3], [3, 2, 1, 0], [1, 3, 0, 2]].iter() {
+                let mut stages = Stages::default();
+                let staged: HashSet<Move> = order.iter().flat_map(|&stage| state.generate_stage(stage, &mut stages).unwrap()).collect();
+                assert_eq!(staged, state.generate_moves().into_iter().collect());
+            }
+        }
+    }
+
+    #[test]
+    fn stages_hold_the_generated_moves() {
+        use rand::{thread_rng, Rng};
+
+        let mut rng = thread_rng();
+        let mut state = super::State::new(2);
+        let mut plies = 0;
+        // Random players can end up passing forever
+        while !state.is_terminal() && plies < 200 {
+            for &prune in [false, true].iter() {
+                let mut moves = state.generate_moves();
+                if prune {
+                    state.prune(&mut moves);
+                }
+                let mut staged = Vec::new();
+                let mut stage = 0;
+                let mut stages = Stages::default();
+                while let Some(mut moves) = state.generate_stage(stage, &mut stages) {
+                    if prune {
+                        state.prune(&mut moves);
+                    }
+                    assert!(moves.iter().all(|mov| state.stage_of(mov) == stage));
+                    staged.append(&mut moves);
+                    stage += 1;
+                }
+                assert_eq!(staged.len(), moves.len());
+                assert!(staged.iter().all(|mov| moves.contains(mov)));
+            }
+
+            let mov = *rng.choose(&state.generate_moves()).unwrap();
+            state.apply(&mov);
+            state.refill_market();
+            plies += 1;
+        }
+    }
+
+    #[test]
+    fn alphabeta_matches_minimax() {
+        let config = algo::SearchConfig {
+            depth: 3,
+            ..algo::SearchConfig::default()
+        };
+        let mut state = super::State::new(2);
+        let result = algo::alphabeta(&mut state, &algo::ScoreEvaluator, &config);
+        let expected = algo::minimax(&mut state, &algo::ScoreEvaluator, &config);
+        assert_eq!(result.score, expected.score);
+        assert!(result.moves.iter().all(|mov| expected.moves.contains(mov)));
+    }
+
+    #[test]
+    fn endgame_finds_forced_win() {
+        use algo::Endgame;
+
+        let mut state = super::State::new(2);
+        let card = |points| Card {
+            color: Color::Blue,
+            cost: Tokens::empty(),
+            points,
+        };
+        state.player.cards.push(card(13));
+        state.adversary.cards.push(card(12));
+        state.cards1[0] = card(2);
+        assert!(state.is_endgame());
+        assert_eq!(state.outcome(), None);
+
+        let config = algo::SearchConfig::default();
+        let result = algo::endgame(&mut state, 3, &config).unwrap();
+        assert_eq!(result.score, algo::Outcome::Win);
+        assert_eq!(result.depth, 1);
+        assert!(result.moves.iter().all(|mov| matches!(*mov, Move::Buy { index: 0, deck: Deck::One, .. })));
+
+        // Nothing is proven once the search is stopped or out of nodes
+        config.stop.stop();
+        assert!(algo::endgame(&mut state, 3, &config).is_none());
+        let config = algo::SearchConfig {
+            node_limit: Some(1),
+            ..algo::SearchConfig::default()
+        };
+        assert!(algo::endgame(&mut state, 3, &config).is_none());
+
+        state.apply(&result.moves[0]);
+        assert_eq!(state.outcome(), Some(algo::Outcome::Win));
+    }
+
     #[test]
     fn can_buy() {
         let player = Player {
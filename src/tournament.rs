@@ -0,0 +1,295 @@
+use std::fmt;
+use agent::{Agent, Game, GreedyAgent, IsmctsAgent, Kind, RandomAgent, SearchAgent};
+use algo::{Endgame, Outcome, SearchConfig};
+use eval::Weights;
+use record::{GameRecord, Setup};
+
+// Games longer than this are counted as a draw
+pub const MAXIMUM_PLIES: usize = 200;
+
+// A bot taking part in a tournament, described by the command line options it plays with
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Bot {
+    pub kind: Kind,
+    pub name: String,
+    pub search: SearchConfig,
+    pub weights: Weights,
+}
+
+impl Bot {
+    // Parses options like `--depth 4 --weights tuned.txt`, the search options of `SearchConfig::set`
+    // and `--weights FILE`, optionally after the kind of bot, like `random`. The bot is named after
+    // them.
+    pub fn parse(spec: &str) -> Result<Bot, String> {
+        Bot::parse_with(spec, true)
+    }
+
+    // Like `parse` but without `--weights`, for bots described by someone who mustn't read files
+    pub fn parse_without_files(spec: &str) -> Result<Bot, String> {
+        Bot::parse_with(spec, false)
+    }
+
+    fn parse_with(spec: &str, files: bool) -> Result<Bot, String> {
+        let mut bot = Bot {
+            kind: Kind::Search,
+            name: spec.trim().to_string(),
+            search: SearchConfig::default(),
+            weights: Weights::default(),
+        };
+        if bot.name.is_empty() {
+            bot.name = "default".to_string();
+        }
+
+        let mut words = spec.split_whitespace().peekable();
+        if let Some(kind) = words.peek().and_then(|word| word.parse().ok()) {
+            bot.kind = kind;
+            words.next();
+        }
+        while let Some(name) = words.next() {
+            if bot.kind == Kind::Random || bot.kind == Kind::Greedy {
+                return Err(format!("{} bots don't take options", bot.kind));
+            }
+            if !files && name.trim_start_matches('-') == "weights" {
+                return Err(format!("{} isn't allowed here", name));
+            }
+            let value = words.next().ok_or_else(|| format!("missing a value for {}", name))?;
+            if name == "--weights" {
+                bot.weights = Weights::load(value).map_err(|err| format!("couldn't load {}: {}", value, err))?;
+            } else if !bot.search.set(name, value)? {
+                return Err(format!("unknown option {}", name));
+            }
+        }
+        Ok(bot)
+    }
+
+    // An agent that plays like the bot, with its randomness seeded with `seed`
+    pub fn agent(&self, seed: u64) -> Box<dyn Agent> {
+        match self.kind {
+            Kind::Search => {
+                let mut agent = SearchAgent::new(&self.name, self.search.clone(), self.weights);
+                agent.seed(seed);
+                Box::new(agent)
+            },
+            Kind::Ismcts => Box::new(IsmctsAgent::new(&self.name, self.search.clone(), self.weights, seed)),
+            Kind::Random => Box::new(RandomAgent::new(&self.name, seed)),
+            Kind::Greedy => Box::new(GreedyAgent::new(&self.name, seed)),
+        }
+    }
+}
+
+// Plays a game on the deal of `seed`. Ties between moves are broken with generators seeded with it
+// too, so a game can be replayed as long as the bots don't have a time limit. Fails if a bot chooses
+// a move that isn't legal.
+pub fn play(player: &Bot, adversary: &Bot, seed: u64) -> Result<GameRecord, String> {
+    let mut game = Game::new(Setup::Seed(seed), player.agent(seed), adversary.agent(seed.wrapping_add(1)));
+    game.play(MAXIMUM_PLIES)?;
+    Ok(game.record)
+}
+
+// How a bot did over a number of games
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(PartialEq)]
+pub struct Stats {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub plies: u64,
+    pub points: u64,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    // Adds a game of `plies` plies in which the bot scored `points`
+    pub fn add(&mut self, outcome: Outcome, plies: usize, points: u8) {
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Loss => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+        self.plies += plies as u64;
+        self.points += points as u64;
+    }
+
+    pub fn merge(&mut self, other: &Stats) {
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.draws += other.draws;
+        self.plies += other.plies;
+        self.points += other.points;
+    }
+
+    // The average result of a game, a win counts 1 and a draw half
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    // Half the width of the 95% confidence interval of `score`, from the normal approximation
+    pub fn margin(&self) -> f64 {
+        let games = self.games() as f64;
+        if games < 2.0 {
+            return 1.0;
+        }
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2) +
+            self.draws as f64 * (0.5 - score).powi(2) +
+            self.losses as f64 * score.powi(2)) / (games - 1.0);
+        1.96 * (variance / games).sqrt()
+    }
+
+    pub fn average_plies(&self) -> f64 {
+        self.plies as f64 / (self.games() as f64).max(1.0)
+    }
+
+    pub fn average_points(&self) -> f64 {
+        self.points as f64 / (self.games() as f64).max(1.0)
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "+{} -{} ={} score {:.3} ± {:.3} plies {:.1} points {:.1}",
+               self.wins, self.losses, self.draws, self.score(), self.margin(), self.average_plies(), self.average_points())
+    }
+}
+
+// The results of every bot against every other one. `stats[i][j]` is how bot `i` did against bot
+// `j`.
+pub struct Tournament {
+    pub bots: Vec<Bot>,
+    pub stats: Vec<Vec<Stats>>,
+}
+
+impl Tournament {
+    pub fn new(bots: Vec<Bot>) -> Tournament {
+        let stats = vec![vec![Stats::new(); bots.len()]; bots.len()];
+        Tournament {
+            bots,
+            stats,
+        }
+    }
+
+    // Plays `games` games between every pair of bots. Seats are swapped after every game and each
+    // deal is played twice, once from each seat, so neither bot gets the luckier cards. An odd
+    // number of games is rounded up so the last deal is played from both seats too. `seeds` gives
+    // the deals and `done` is called after every game. Stops at the first game a bot chooses a move
+    // that isn't legal in.
+    pub fn run<S, F>(&mut self, games: usize, mut seeds: S, mut done: F) -> Result<(), String> where S: FnMut() -> u64, F: FnMut(&Tournament, usize, usize, &GameRecord) {
+        for i in 0..self.bots.len() {
+            for j in i + 1..self.bots.len() {
+                let mut seed = 0;
+                for game in 0..games + games % 2 {
+                    if game % 2 == 0 {
+                        seed = seeds();
+                    }
+                    let (first, second) = if game % 2 == 0 { (i, j) } else { (j, i) };
+                    let record = play(&self.bots[first], &self.bots[second], seed)?;
+                    self.add(first, second, &record);
+                    done(self, first, second, &record);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Adds a game in which bot `player` moved first and bot `adversary` second
+    pub fn add(&mut self, player: usize, adversary: usize, record: &GameRecord) {
+        let state = match record.replay() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        let outcome = state.outcome().unwrap_or(Outcome::Draw);
+        let plies = record.moves.len();
+        self.stats[player][adversary].add(outcome, plies, state.player.score());
+        self.stats[adversary][player].add(outcome.opposite(), plies, state.adversary.score());
+    }
+
+    // How bot `i` did against all the others
+    pub fn total(&self, i: usize) -> Stats {
+        let mut total = Stats::new();
+        for stats in self.stats[i].iter() {
+            total.merge(stats);
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut stats = Stats::new();
+        assert_eq!(stats.score(), 0.0);
+        stats.add(Outcome::Win, 40, 15);
+        stats.add(Outcome::Win, 50, 16);
+        stats.add(Outcome::Draw, 60, 12);
+        stats.add(Outcome::Loss, 50, 9);
+        assert_eq!(stats.games(), 4);
+        assert_eq!(stats.score(), 0.625);
+        assert_eq!(stats.average_plies(), 50.0);
+        assert_eq!(stats.average_points(), 13.0);
+        assert!(stats.margin() > 0.0 && stats.margin() < 1.0);
+
+        // More games of the same kind narrow the interval
+        let mut more = stats.clone();
+        for _ in 0..9 {
+            more.merge(&stats);
+        }
+        assert_eq!(more.score(), 0.625);
+        assert!(more.margin() < stats.margin() / 2.0);
+    }
+
+    #[test]
+    fn parse_bot() {
+        let bot = Bot::parse("--depth 2 --tie-break first").unwrap();
+        assert_eq!(bot.name, "--depth 2 --tie-break first");
+        assert_eq!(bot.search.depth, 2);
+        assert_eq!(Bot::parse("").unwrap().name, "default");
+        assert_eq!(Bot::parse("greedy").unwrap().kind, Kind::Greedy);
+        assert_eq!(Bot::parse("search --depth 3").unwrap().search.depth, 3);
+        assert!(Bot::parse("random --depth 3").is_err());
+        let bot = Bot::parse("ismcts --nodes 500").unwrap();
+        assert_eq!((bot.kind, bot.search.node_limit), (Kind::Ismcts, Some(500)));
+        assert!(Bot::parse("--depth").is_err());
+        assert!(Bot::parse("--games 2").is_err());
+        assert_eq!(Bot::parse_without_files("--depth 3").unwrap().search.depth, 3);
+        assert!(Bot::parse_without_files("--depth 3 --weights tuned.txt").is_err());
+        assert!(Bot::parse_without_files("weights tuned.txt").is_err());
+    }
+
+    #[test]
+    fn swap_seats() {
+        let bots = vec![Bot::parse("--depth 1").unwrap(), Bot::parse("--depth 2").unwrap()];
+        let mut tournament = Tournament::new(bots);
+        let mut seeds = 0;
+        let mut games = Vec::new();
+        // Rounded up so the second deal is played from both seats
+        let result = tournament.run(3, || { seeds += 1; seeds }, |_, player, adversary, record| {
+            games.push((player, adversary, record.setup.clone()));
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(games, vec![
+            (0, 1, Setup::Seed(1)),
+            (1, 0, Setup::Seed(1)),
+            (0, 1, Setup::Seed(2)),
+            (1, 0, Setup::Seed(2)),
+        ]);
+        assert_eq!(tournament.stats[0][1].games(), 4);
+        assert_eq!(tournament.stats[0][1].wins, tournament.stats[1][0].losses);
+        assert_eq!(tournament.total(0), tournament.stats[0][1]);
+    }
+}